@@ -1,16 +1,66 @@
 use alloy_sol_types::sol;
+use sha2::{Digest, Sha256};
 use std::cmp::{min, max};
 
-const FILTER_BITS: i32 = 14;
+pub const FILTER_BITS: i32 = 14;
 const FILTER_SCALE: i32 = 1 << FILTER_BITS;
 
+/// The `transformId` of a resize with `scale_image`.
+pub const TRANSFORM_RESIZE: u32 = 1;
+
 sol! {
     /// The public values encoded as a struct that can be easily deserialized inside Solidity.
+    ///
+    /// The program attests that the image hashing to `outputHash` is the transform `transformId`
+    /// of the image hashing to `sourceHash`, see `image_hash`.
     struct PublicValuesStruct {
-        uint32 n;
-        uint32 a;
-        uint32 b;
+        bytes32 sourceHash;
+        bytes32 outputHash;
+        uint32 sourceWidth;
+        uint32 sourceHeight;
+        uint32 outputWidth;
+        uint32 outputHeight;
+        uint32 transformId;
+    }
+}
+
+/// The SHA-256 digest of the row-major 8-bit pixels of a grayscale image.
+pub fn image_hash(pixels: &[u8]) -> [u8; 32] {
+    Sha256::digest(pixels).into()
+}
+
+/// Resizes a `src_w` x `src_h` image to `dst_w` x `dst_h`, returning the resized pixels and the
+/// public values attesting to it, or `None` if the dimensions are invalid.
+///
+/// This is what the program runs inside the zkVM, so the host can check a proof's public values
+/// against a native run.
+pub fn attest_resize(
+    pixels: &[u8],
+    src_w: u32,
+    src_h: u32,
+    dst_w: u32,
+    dst_h: u32,
+) -> Option<(Vec<u8>, PublicValuesStruct)> {
+    let dimensions = [src_w, src_h, dst_w, dst_h];
+    if dimensions.iter().any(|&d| d == 0 || d > i32::MAX as u32)
+        || pixels.len() != src_w as usize * src_h as usize
+    {
+        return None;
     }
+    let c = Context::new(src_w as i32, src_h as i32, dst_w as i32, dst_h as i32)?;
+    let mut output = vec![0u8; dst_w as usize * dst_h as usize];
+    scale_image(&c, pixels, src_w as i32, &mut output, dst_w as i32);
+
+    let public_values = PublicValuesStruct {
+        sourceHash: image_hash(pixels).into(),
+        outputHash: image_hash(&output).into(),
+        sourceWidth: src_w,
+        sourceHeight: src_h,
+        outputWidth: dst_w,
+        outputHeight: dst_h,
+        transformId: TRANSFORM_RESIZE,
+    };
+    Some((output, public_values))
 }
 
 pub fn print_data_sample(data: &[u8], sample_size: usize) {
@@ -117,7 +167,7 @@ pub struct Context {
 }
 
 impl Context {
-    fn new(src_w: i32, src_h: i32, dst_w: i32, dst_h: i32) -> Option<Self> {
+    pub fn new(src_w: i32, src_h: i32, dst_w: i32, dst_h: i32) -> Option<Self> {
         let filter_size = 4;
         let mut context = Context {
             filter_pos: Vec::new(),
@@ -140,6 +190,27 @@ impl Context {
         Some(context)
     }
 
+    /// Returns whether every filter starts inside the source. `scale_image` skips the taps past
+    /// the end of the source but not those before its start, so it can't resize otherwise: the
+    /// filter of the first row starts above the image unless the height is divided by more than 3.
+    pub fn starts_inside_source(&self) -> bool {
+        self.filter_pos.iter().chain(&self.v_lum_filter_pos).all(|&pos| pos >= 0)
+    }
+
+    /// Returns the first source column and the coefficients of the horizontal filter of output
+    /// column `x`. Taps past the last column are skipped by `scale_image`.
+    pub fn horizontal_filter(&self, x: usize) -> (i32, &[i16]) {
+        let taps = &self.filter[x * self.filter_size..(x + 1) * self.filter_size];
+        (self.filter_pos[x], taps)
+    }
+
+    /// Returns the first source row and the coefficients of the vertical filter of output row `y`.
+    /// Taps past the last row are skipped by `scale_image`.
+    pub fn vertical_filter(&self, y: usize) -> (i32, &[i16]) {
+        let taps = &self.v_lum_filter[y * self.v_lum_filter_size..(y + 1) * self.v_lum_filter_size];
+        (self.v_lum_filter_pos[y], taps)
+    }
+
     fn init_filter(&mut self, src_w: i32, dst_w: i32, filter_size: usize) -> Result<(), ()> {
         let x_inc: i64 = (((src_w as i64) << 16) / dst_w as i64 + 1) >> 1;
 
@@ -218,16 +289,3 @@ impl Context {
         }
     }
 }
-
-
-/// Compute the n'th fibonacci number (wrapping around on overflows), using normal Rust code.
-pub fn fibonacci(n: u32) -> (u32, u32) {
-    let mut a = 0u32;
-    let mut b = 1u32;
-    for _ in 0..n {
-        let c = a.wrapping_add(b);
-        a = b;
-        b = c;
-    }
-    (a, b)
-}
@@ -0,0 +1,316 @@
+//! Proving every frame of a directory, as written by `decompose_video_to_frams.py`.
+//!
+//! Frames are proved by a bounded number of workers. Each proved frame is recorded in a checkpoint
+//! file in the output directory, so that an interrupted batch resumes where it stopped: frames in
+//! the checkpoint are skipped, and failed frames are retried. Once every frame is proved, a
+//! manifest lists the proofs with their public values.
+
+use crate::bundle::AttestedResize;
+use anyhow::{ensure, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// The name of the checkpoint file in the output directory.
+pub const CHECKPOINT_FILE: &str = "checkpoint.json";
+
+/// The name of the manifest file in the output directory.
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// The extensions of the frames, see `load_image`.
+const FRAME_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "txt"];
+
+/// What the frames are proved with. A checkpoint can only be resumed with the same settings.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BatchSettings {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub scale: Option<f64>,
+    pub compressed: bool,
+    /// The hash of the program verification key.
+    pub vkey_hash: String,
+}
+
+/// A proved frame. The paths are relative to the output directory.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FrameProof {
+    /// The file name of the frame.
+    pub frame: String,
+    /// The proof with its public values.
+    pub proof: PathBuf,
+    /// The resized frame.
+    pub output_image: PathBuf,
+    pub public_values: AttestedResize,
+}
+
+/// The frames proved so far.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+    pub settings: BatchSettings,
+    /// The proved frames by file name.
+    pub frames: BTreeMap<String, FrameProof>,
+}
+
+/// The proofs of all the frames of a directory.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BatchManifest {
+    pub sp1_version: String,
+    /// The SHA-256 digest of the program ELF, hex encoded.
+    pub elf_hash: String,
+    pub settings: BatchSettings,
+    /// The proved frames, in the order of their file names.
+    pub frames: Vec<FrameProof>,
+}
+
+/// The outcome of `prove_frames`.
+#[derive(Debug, Default)]
+pub struct BatchOutcome {
+    /// The number of frames proved by this run.
+    pub proved: usize,
+    /// The number of frames already proved in the checkpoint.
+    pub resumed: usize,
+    /// The frames that failed, with their errors.
+    pub failed: Vec<(String, anyhow::Error)>,
+}
+
+/// The frames of a directory, sorted by file name. The frames must have different file stems,
+/// which name their proofs.
+pub fn frame_paths(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut frames = Vec::new();
+    for entry in
+        fs::read_dir(dir).with_context(|| format!("cannot read the directory {}", dir.display()))?
+    {
+        let path = entry?.path();
+        let is_frame = path.extension().is_some_and(|extension| {
+            FRAME_EXTENSIONS
+                .iter()
+                .any(|frame_extension| extension.eq_ignore_ascii_case(frame_extension))
+        });
+        if is_frame && path.is_file() {
+            frames.push(path);
+        }
+    }
+    frames.sort();
+    let mut stems = HashSet::new();
+    for frame in &frames {
+        ensure!(
+            stems.insert(frame.file_stem()),
+            "several frames are named {}",
+            frame.with_extension("").display()
+        );
+    }
+    Ok(frames)
+}
+
+/// The file name of a frame, which identifies it in the checkpoint.
+pub fn frame_name(frame: &Path) -> Result<String> {
+    frame
+        .file_name()
+        .and_then(|name| name.to_str())
+        .map(str::to_string)
+        .with_context(|| format!("invalid frame name {}", frame.display()))
+}
+
+impl Checkpoint {
+    /// Reads the checkpoint at `path`, or starts a new one if there is none.
+    pub fn load_or_new(path: &Path, settings: BatchSettings) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Checkpoint {
+                settings,
+                frames: BTreeMap::new(),
+            });
+        }
+        let contents =
+            fs::read_to_string(path).with_context(|| format!("cannot read {}", path.display()))?;
+        let checkpoint: Checkpoint = serde_json::from_str(&contents)
+            .with_context(|| format!("{} is not a checkpoint", path.display()))?;
+        ensure!(
+            checkpoint.settings == settings,
+            "{} was made with other settings, use another output directory or delete it",
+            path.display()
+        );
+        Ok(checkpoint)
+    }
+
+    /// Writes the checkpoint. The file is replaced at once, so that an interruption doesn't leave
+    /// it truncated.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let temp = path.with_extension("json.tmp");
+        fs::write(&temp, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("cannot write {}", temp.display()))?;
+        fs::rename(&temp, path).with_context(|| format!("cannot write {}", path.display()))
+    }
+}
+
+/// Proves the frames not in the checkpoint with `prove`, running at most `jobs` at a time. The
+/// checkpoint is saved to `checkpoint_path` after each proved frame.
+pub fn prove_frames<F>(
+    frames: &[PathBuf],
+    jobs: usize,
+    checkpoint: &mut Checkpoint,
+    checkpoint_path: &Path,
+    prove: F,
+) -> Result<BatchOutcome>
+where
+    F: Fn(&Path) -> Result<FrameProof> + Sync,
+{
+    let mut pending = Vec::new();
+    for frame in frames {
+        let name = frame_name(frame)?;
+        if !checkpoint.frames.contains_key(&name) {
+            pending.push((name, frame.as_path()));
+        }
+    }
+    let resumed = frames.len() - pending.len();
+
+    let next = AtomicUsize::new(0);
+    let checkpoint = Mutex::new(checkpoint);
+    let failed = Mutex::new(Vec::new());
+    thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, pending.len().max(1)) {
+            scope.spawn(|| {
+                while let Some((name, frame)) = pending.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let result = prove(frame).and_then(|proof| {
+                        let mut checkpoint = checkpoint.lock().unwrap();
+                        checkpoint.frames.insert(name.clone(), proof);
+                        checkpoint.save(checkpoint_path)
+                    });
+                    if let Err(err) = result {
+                        failed.lock().unwrap().push((name.clone(), err));
+                    }
+                }
+            });
+        }
+    });
+
+    let mut failed = failed.into_inner().unwrap();
+    failed.sort_by(|(a, _), (b, _)| a.cmp(b));
+    Ok(BatchOutcome {
+        proved: pending.len() - failed.len(),
+        resumed,
+        failed,
+    })
+}
+
+impl BatchManifest {
+    /// The manifest of the frames of a checkpoint.
+    pub fn new(checkpoint: &Checkpoint, sp1_version: String, elf_hash: [u8; 32]) -> Self {
+        BatchManifest {
+            sp1_version,
+            elf_hash: format!("0x{}", hex::encode(elf_hash)),
+            settings: checkpoint.settings.clone(),
+            frames: checkpoint.frames.values().cloned().collect(),
+        }
+    }
+
+    /// Writes the manifest.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("cannot write {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> BatchSettings {
+        BatchSettings {
+            width: None,
+            height: None,
+            scale: Some(0.5),
+            compressed: false,
+            vkey_hash: "0xaa".to_string(),
+        }
+    }
+
+    fn frame_proof(frame: &Path) -> FrameProof {
+        FrameProof {
+            frame: frame_name(frame).unwrap(),
+            proof: frame.with_extension("bin"),
+            output_image: frame.with_extension("png"),
+            public_values: AttestedResize {
+                source_hash: [1; 32],
+                output_hash: [2; 32],
+                source_width: 4,
+                source_height: 4,
+                output_width: 2,
+                output_height: 2,
+                transform_id: 1,
+            },
+        }
+    }
+
+    #[test]
+    fn test_frame_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in [
+            "frame_0001.jpg",
+            "frame_0000.jpg",
+            "notes.md",
+            "frame_0002.PNG",
+        ] {
+            fs::write(dir.path().join(name), b"").unwrap();
+        }
+        let frames = frame_paths(dir.path()).unwrap();
+        let names: Vec<String> = frames.iter().map(|f| frame_name(f).unwrap()).collect();
+        assert_eq!(
+            names,
+            ["frame_0000.jpg", "frame_0001.jpg", "frame_0002.PNG"]
+        );
+
+        // Proofs are named after the stems
+        fs::write(dir.path().join("frame_0000.png"), b"").unwrap();
+        assert!(frame_paths(dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_resume() {
+        let dir = tempfile::tempdir().unwrap();
+        let checkpoint_path = dir.path().join(CHECKPOINT_FILE);
+        let frames: Vec<PathBuf> = (0..7)
+            .map(|i| PathBuf::from(format!("frame_{:04}.jpg", i)))
+            .collect();
+
+        // The first run fails on some frames
+        let attempts = AtomicUsize::new(0);
+        let mut checkpoint = Checkpoint::load_or_new(&checkpoint_path, settings()).unwrap();
+        let outcome = prove_frames(&frames, 3, &mut checkpoint, &checkpoint_path, |frame| {
+            attempts.fetch_add(1, Ordering::Relaxed);
+            ensure!(!frame.ends_with("frame_0003.jpg"), "failed");
+            Ok(frame_proof(frame))
+        })
+        .unwrap();
+        assert_eq!(attempts.into_inner(), 7);
+        assert_eq!((outcome.proved, outcome.resumed), (6, 0));
+        assert_eq!(outcome.failed.len(), 1);
+        assert_eq!(outcome.failed[0].0, "frame_0003.jpg");
+
+        // The second run only proves the failed frame
+        let mut checkpoint = Checkpoint::load_or_new(&checkpoint_path, settings()).unwrap();
+        assert_eq!(checkpoint.frames.len(), 6);
+        let outcome = prove_frames(&frames, 3, &mut checkpoint, &checkpoint_path, |frame| {
+            assert!(frame.ends_with("frame_0003.jpg"));
+            Ok(frame_proof(frame))
+        })
+        .unwrap();
+        assert_eq!((outcome.proved, outcome.resumed), (1, 6));
+        assert!(outcome.failed.is_empty());
+
+        let manifest = BatchManifest::new(&checkpoint, "v1.1.0".to_string(), [0; 32]);
+        let names: Vec<&str> = manifest.frames.iter().map(|f| f.frame.as_str()).collect();
+        assert_eq!(names.len(), 7);
+        assert!(names.windows(2).all(|pair| pair[0] < pair[1]));
+
+        // A checkpoint can't be resumed with other settings
+        let other = BatchSettings {
+            compressed: true,
+            ..settings()
+        };
+        assert!(Checkpoint::load_or_new(&checkpoint_path, other).is_err());
+    }
+}
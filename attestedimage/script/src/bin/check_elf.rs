@@ -0,0 +1,112 @@
+//! A script to check that the committed ELF is the program in `program`: it rebuilds the program,
+//! and compares the hash and the verification key of the rebuilt ELF with those of the ELF
+//! committed in `elf`.
+//!
+//! You can run this script using the following command:
+//! ```shell
+//! RUST_LOG=info cargo run --release --bin check-elf -- --docker
+//! ```
+//!
+//! The script exits with a nonzero code if the ELFs differ. Building in Docker pins the toolchain,
+//! so that the ELF is the same on every machine.
+
+use anyhow::{ensure, Context, Result};
+use clap::Parser;
+use sha2::{Digest, Sha256};
+use sp1_build::BuildArgs;
+use sp1_sdk::{HashableKey, ProverClient};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode};
+
+/// The directory of the program.
+const PROGRAM_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../program");
+
+/// The committed ELF, relative to the root of the workspace.
+const COMMITTED_ELF: &str = "elf/riscv32im-succinct-zkvm-elf";
+
+/// Where the rebuilt ELF is written, relative to the root of the workspace, so that building it
+/// doesn't overwrite the committed ELF.
+const REBUILT_ELF_DIR: &str = "target/elf-check";
+
+/// The arguments for the check-elf command.
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct CheckElfArgs {
+    /// Build the program in the SP1 Docker image, for a reproducible ELF.
+    #[arg(long)]
+    docker: bool,
+    /// The tag of the SP1 Docker image, which should match the version of `sp1-zkvm`.
+    #[arg(long, default_value = "v2.0.0")]
+    tag: String,
+    /// Compare with this ELF instead of the one committed in git. The build script of the script
+    /// overwrites `elf` unless `SP1_SKIP_PROGRAM_BUILD` is set, so the file in the working tree
+    /// may not be the committed one.
+    #[arg(long)]
+    elf: Option<PathBuf>,
+}
+
+fn main() -> Result<ExitCode> {
+    // Setup the logger.
+    sp1_sdk::utils::setup_logger();
+
+    // Parse the command line arguments.
+    let args = CheckElfArgs::parse();
+
+    let committed = match &args.elf {
+        Some(path) => fs::read(path).with_context(|| format!("cannot read {}", path.display()))?,
+        None => committed_elf()?,
+    };
+
+    // Rebuild the program.
+    let build_args = BuildArgs {
+        docker: args.docker,
+        tag: args.tag.clone(),
+        output_directory: REBUILT_ELF_DIR.to_string(),
+        ..Default::default()
+    };
+    let path = sp1_build::execute_build_program(&build_args, Some(PathBuf::from(PROGRAM_DIR)))
+        .context("failed to build the program")?;
+    let rebuilt = fs::read(&path).with_context(|| format!("cannot read {}", path))?;
+    println!("Program rebuilt to {}", path);
+
+    // Only the verification keys are needed, which don't depend on the prover.
+    let client = ProverClient::mock();
+    let (_, committed_vk) = client.setup(&committed);
+    let (_, rebuilt_vk) = client.setup(&rebuilt);
+
+    let committed_hash = hex::encode(Sha256::digest(&committed));
+    let rebuilt_hash = hex::encode(Sha256::digest(&rebuilt));
+    println!("Committed ELF hash: 0x{}", committed_hash);
+    println!("Rebuilt ELF hash: 0x{}", rebuilt_hash);
+    println!("Committed verification key: {}", committed_vk.bytes32());
+    println!("Rebuilt verification key: {}", rebuilt_vk.bytes32());
+
+    if committed_hash != rebuilt_hash || committed_vk.bytes32() != rebuilt_vk.bytes32() {
+        eprintln!(
+            "error: the committed ELF is not the program in {}, rebuild it with `cargo prove build \
+             --docker --tag {}` and commit it",
+            Path::new(PROGRAM_DIR).display(),
+            args.tag
+        );
+        return Ok(ExitCode::FAILURE);
+    }
+    println!("The committed ELF matches the program.");
+    Ok(ExitCode::SUCCESS)
+}
+
+/// The ELF committed at `HEAD`.
+fn committed_elf() -> Result<Vec<u8>> {
+    let workspace = Path::new(PROGRAM_DIR).join("..");
+    let output = Command::new("git")
+        .current_dir(&workspace)
+        .args(["show", &format!("HEAD:./{}", COMMITTED_ELF)])
+        .output()
+        .context("failed to run git, pass the ELF with --elf")?;
+    ensure!(
+        output.status.success(),
+        "cannot read the committed ELF: {}",
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+    Ok(output.stdout)
+}
@@ -3,79 +3,115 @@
 //!
 //! You can run this script using the following command:
 //! ```shell
-//! RUST_LOG=info cargo run --release --bin evm
+//! RUST_LOG=info cargo run --release --bin evm -- --input ../../resizing_video/sand_480270.jpg \
+//!     --scale 0.25 --output fixtures/plonk-fixture.json
 //! ```
 
-use alloy_sol_types::SolType;
-use clap::Parser;
-use fibonacci_lib::PublicValuesStruct;
+use anyhow::{Context, Result};
+use clap::{Parser, ValueEnum};
+use fibonacci_script::{
+    decode_public_values, load_image, print_public_values, program_input, ProverArgs, ResizeArgs,
+    RESIZE_ELF,
+};
 use serde::{Deserialize, Serialize};
-use sp1_sdk::{HashableKey, ProverClient, SP1ProofWithPublicValues, SP1Stdin, SP1VerifyingKey};
-use std::path::PathBuf;
-
-/// The ELF (executable and linkable format) file for the Succinct RISC-V zkVM.
-pub const FIBONACCI_ELF: &[u8] = include_bytes!("../../../elf/riscv32im-succinct-zkvm-elf");
+use sp1_sdk::{HashableKey, SP1ProofWithPublicValues, SP1VerifyingKey};
+use std::fs;
+use std::path::{Path, PathBuf};
 
 /// The arguments for the EVM command.
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
 struct EVMArgs {
-    #[clap(long, default_value = "20")]
-    n: u32,
+    #[command(flatten)]
+    resize: ResizeArgs,
+    /// The proof system wrapping the proof for the EVM.
+    #[arg(long, value_enum, default_value_t = ProofSystem::Plonk)]
+    system: ProofSystem,
+    /// Where to write the fixture.
+    #[arg(long)]
+    output: PathBuf,
+
+    #[command(flatten)]
+    prover: ProverArgs,
+}
+
+/// A proof system the EVM verifier of SP1 supports.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ProofSystem {
+    Plonk,
+    Groth16,
 }
 
-/// A fixture that can be used to test the verification of SP1 zkVM proofs inside Solidity.
+/// A fixture that can be used to test the verification of image attestations inside Solidity.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct SP1FibonacciProofFixture {
-    a: u32,
-    b: u32,
-    n: u32,
+struct SP1ImageAttestationFixture {
+    system: ProofSystem,
+    source_hash: String,
+    output_hash: String,
+    source_width: u32,
+    source_height: u32,
+    output_width: u32,
+    output_height: u32,
+    transform_id: u32,
     vkey: String,
     public_values: String,
     proof: String,
 }
 
-fn main() {
+fn main() -> Result<()> {
     // Setup the logger.
     sp1_sdk::utils::setup_logger();
 
     // Parse the command line arguments.
     let args = EVMArgs::parse();
 
-    // Setup the prover client.
-    let client = ProverClient::new();
-
-    // Setup the program.
-    let (pk, vk) = client.setup(FIBONACCI_ELF);
-
     // Setup the inputs.
-    let mut stdin = SP1Stdin::new();
-    stdin.write(&args.n);
+    let image = load_image(&args.resize.input)?;
+    let (dst_w, dst_h) = args
+        .resize
+        .output_dimensions(image.width(), image.height())?;
+    let stdin = program_input(&image, dst_w, dst_h);
 
-    println!("n: {}", args.n);
+    // Setup the prover client and the program.
+    let client = args.prover.client();
+    let (pk, vk) = client.setup(RESIZE_ELF);
 
     // Generate the proof.
-    let proof = client
-        .prove(&pk, stdin)
-        .plonk()
-        .run()
-        .expect("failed to generate proof");
-
-    create_plonk_fixture(&proof, &vk);
+    let prove = client.prove(&pk, stdin);
+    let proof = match args.system {
+        ProofSystem::Plonk => prove.plonk(),
+        ProofSystem::Groth16 => prove.groth16(),
+    }
+    .run()
+    .context("failed to generate proof")?;
+
+    create_fixture(args.system, &proof, &vk, &args.output)
 }
 
 /// Create a fixture for the given proof.
-fn create_plonk_fixture(proof: &SP1ProofWithPublicValues, vk: &SP1VerifyingKey) {
+fn create_fixture(
+    system: ProofSystem,
+    proof: &SP1ProofWithPublicValues,
+    vk: &SP1VerifyingKey,
+    path: &Path,
+) -> Result<()> {
     // Deserialize the public values.
     let bytes = proof.public_values.as_slice();
-    let PublicValuesStruct { n, a, b } = PublicValuesStruct::abi_decode(bytes, false).unwrap();
+    let public_values = decode_public_values(bytes)?;
+    print_public_values(&public_values);
 
     // Create the testing fixture so we can test things end-to-end.
-    let fixture = SP1FibonacciProofFixture {
-        a,
-        b,
-        n,
+    let fixture = SP1ImageAttestationFixture {
+        system,
+        source_hash: public_values.sourceHash.to_string(),
+        output_hash: public_values.outputHash.to_string(),
+        source_width: public_values.sourceWidth,
+        source_height: public_values.sourceHeight,
+        output_width: public_values.outputWidth,
+        output_height: public_values.outputHeight,
+        transform_id: public_values.transformId,
         vkey: vk.bytes32().to_string(),
         public_values: format!("0x{}", hex::encode(bytes)),
         proof: format!("0x{}", hex::encode(proof.bytes())),
@@ -98,11 +134,12 @@ fn create_plonk_fixture(proof: &SP1ProofWithPublicValues, vk: &SP1VerifyingKey)
     println!("Proof Bytes: {}", fixture.proof);
 
     // Save the fixture to a file.
-    let fixture_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../contracts/src/fixtures");
-    std::fs::create_dir_all(&fixture_path).expect("failed to create fixture path");
-    std::fs::write(
-        fixture_path.join("fixture.json"),
-        serde_json::to_string_pretty(&fixture).unwrap(),
-    )
-    .expect("failed to write fixture");
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("cannot create the directory {}", dir.display()))?;
+    }
+    fs::write(path, serde_json::to_string_pretty(&fixture)?)
+        .with_context(|| format!("cannot write {}", path.display()))?;
+    println!("Fixture written to {}", path.display());
+    Ok(())
 }
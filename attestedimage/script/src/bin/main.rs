@@ -1,91 +1,501 @@
-//! An end-to-end example of using the SP1 SDK to generate a proof of a program that can be executed
-//! or have a core proof generated.
+//! An end-to-end example of using the SP1 SDK to attest that an image was resized: the program can
+//! be executed, or have a core or compressed proof generated.
 //!
 //! You can run this script using the following command:
 //! ```shell
-//! RUST_LOG=info cargo run --release -- --execute
+//! RUST_LOG=info cargo run --release -- execute --input ../../resizing_video/sand_480270.jpg --scale 0.25
 //! ```
 //! or
 //! ```shell
-//! RUST_LOG=info cargo run --release -- --prove
+//! RUST_LOG=info cargo run --release -- prove --input ../../resizing_video/sand_480270.jpg \
+//!     --width 120 --height 68 --output-proof proof.bin
 //! ```
+//!
+//! The `pack`, `unpack` and `inspect` subcommands handle bundles, single files holding a proof,
+//! what identifies the program, and optionally the resized image. `embed` stores a bundle in the
+//! metadata of the PNG or JPEG image it attests to, and `extract` gets it back. `manifest` verifies
+//! a bundle and writes the C2PA-style manifest of the edit it attests to.
+//!
+//! `prove-dir` proves every frame of a directory, resuming from its checkpoint if interrupted:
+//! ```shell
+//! RUST_LOG=info cargo run --release -- prove-dir --input-dir frames --output-dir proofs --scale 0.25
+//! ```
+//!
+//! Pass `--mock` to any subcommand to use the mock prover, which executes the program without
+//! generating a real proof.
 
 use alloy_sol_types::SolType;
-use clap::Parser;
-use fibonacci_lib::PublicValuesStruct;
-use sp1_sdk::{ProverClient, SP1Stdin};
-
-/// The ELF (executable and linkable format) file for the Succinct RISC-V zkVM.
-pub const FIBONACCI_ELF: &[u8] = include_bytes!("../../../elf/riscv32im-succinct-zkvm-elf");
+use anyhow::{ensure, Context, Result};
+use clap::{Parser, Subcommand};
+use fibonacci_lib::{attest_resize, PublicValuesStruct};
+use fibonacci_script::batch::{
+    self, BatchManifest, BatchSettings, Checkpoint, FrameProof, CHECKPOINT_FILE, MANIFEST_FILE,
+};
+use fibonacci_script::bundle::{self, bundle_id, AttestedResize, Bundle};
+use fibonacci_script::c2pa::Manifest;
+use fibonacci_script::embed;
+use fibonacci_script::registry::{Registry, DEFAULT_REGISTRY};
+use fibonacci_script::{
+    check_output_image, decode_public_values, load_image, load_proof, print_public_values,
+    program_input, save_image, save_proof, ProverArgs, ResizeArgs, SizeArgs, RESIZE_ELF,
+};
+use image::GrayImage;
+use sp1_sdk::{HashableKey, ProverClient, SP1Proof, SP1ProvingKey, SP1VerifyingKey};
+use std::fs;
+use std::path::{Path, PathBuf};
 
 /// The arguments for the command.
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
 struct Args {
-    #[clap(long)]
-    execute: bool,
+    #[command(subcommand)]
+    command: Command,
 
-    #[clap(long)]
-    prove: bool,
+    #[command(flatten)]
+    prover: ProverArgs,
+}
 
-    #[clap(long, default_value = "20")]
-    n: u32,
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Execute the program without proving it, and check its public values against a native run.
+    Execute {
+        #[command(flatten)]
+        resize: ResizeArgs,
+    },
+    /// Prove the program, and write the proof and the resized image.
+    Prove {
+        #[command(flatten)]
+        resize: ResizeArgs,
+        /// Where to write the proof with its public values.
+        #[arg(long)]
+        output_proof: PathBuf,
+        /// Where to write the resized image, by default next to the proof as a PNG file.
+        #[arg(long)]
+        output_image: Option<PathBuf>,
+        /// Generate a compressed proof, of constant size, instead of a core proof.
+        #[arg(long)]
+        compressed: bool,
+    },
+    /// Prove every frame of a directory, and write the proofs, the resized frames and a manifest.
+    ProveDir {
+        /// The directory of the frames: PNG, JPEG or text images, e.g. `frame_NNNN.jpg`.
+        #[arg(long)]
+        input_dir: PathBuf,
+        /// The directory to write the proofs, the resized frames, the checkpoint and the manifest
+        /// in. A checkpoint in it is resumed.
+        #[arg(long)]
+        output_dir: PathBuf,
+        #[command(flatten)]
+        size: SizeArgs,
+        /// The number of frames to prove at a time. Each proof already uses every core, so more
+        /// jobs mostly help with the network prover.
+        #[arg(long, default_value_t = 1)]
+        jobs: usize,
+        /// Generate compressed proofs, of constant size, instead of core proofs.
+        #[arg(long)]
+        compressed: bool,
+    },
+    /// Verify a proof, and bundle it with the resized image in a file named after its contents.
+    Pack {
+        /// The proof with its public values, as written by `prove`.
+        #[arg(long)]
+        proof: PathBuf,
+        /// The resized image to include in the bundle.
+        #[arg(long)]
+        image: Option<PathBuf>,
+        /// The directory to write the bundle in.
+        #[arg(long, default_value = ".")]
+        output_dir: PathBuf,
+        /// The registry of pinned programs, whose verification keys are trusted.
+        #[arg(long, default_value = DEFAULT_REGISTRY)]
+        registry: PathBuf,
+    },
+    /// Extract the proof and the resized image of a bundle.
+    Unpack {
+        /// The bundle.
+        bundle: PathBuf,
+        /// The directory to write the proof and the image in.
+        #[arg(long, default_value = ".")]
+        output_dir: PathBuf,
+    },
+    /// Print the contents of a bundle.
+    Inspect {
+        /// The bundle.
+        bundle: PathBuf,
+    },
+    /// Embed a bundle in the metadata of the PNG or JPEG image it attests to.
+    Embed {
+        /// The bundle.
+        bundle: PathBuf,
+        /// The image, which must decode to the resized pixels the bundle attests to. A lossy JPEG
+        /// encoding of them doesn't: embed in a PNG image, or in the exact JPEG bytes that decode to
+        /// the proven pixels.
+        #[arg(long)]
+        image: PathBuf,
+        /// Where to write the image with the bundle.
+        #[arg(long)]
+        output: PathBuf,
+    },
+    /// Extract the bundle embedded in an image.
+    Extract {
+        /// The image.
+        image: PathBuf,
+        /// The directory to write the bundle in.
+        #[arg(long, default_value = ".")]
+        output_dir: PathBuf,
+    },
+    /// Write the C2PA-style manifest of the edit attested by a bundle, carrying its proof.
+    Manifest {
+        /// The bundle.
+        bundle: PathBuf,
+        /// Where to write the manifest, as JSON.
+        #[arg(long)]
+        output: PathBuf,
+        /// The title of the resized image.
+        #[arg(long, default_value = "output.png")]
+        title: String,
+        /// The MIME type of the resized image.
+        #[arg(long, default_value = "image/png")]
+        format: String,
+        /// The title of the source image.
+        #[arg(long, default_value = "source")]
+        source_title: String,
+        /// The registry of pinned programs, whose verification keys are trusted.
+        #[arg(long, default_value = DEFAULT_REGISTRY)]
+        registry: PathBuf,
+    },
 }
 
-fn main() {
+fn main() -> Result<()> {
     // Setup the logger.
     sp1_sdk::utils::setup_logger();
 
     // Parse the command line arguments.
     let args = Args::parse();
 
-    if args.execute == args.prove {
-        eprintln!("Error: You must specify either --execute or --prove");
-        std::process::exit(1);
+    match args.command {
+        Command::Execute { resize } => {
+            let (image, dst_w, dst_h) = load(&resize)?;
+            let (_, expected) = resize_natively(&image, dst_w, dst_h)?;
+
+            // Setup the prover client.
+            let client = args.prover.client();
+
+            // Execute the program
+            let (output, report) = client
+                .execute(RESIZE_ELF, program_input(&image, dst_w, dst_h))
+                .run()
+                .context("failed to execute the program")?;
+            println!("Program executed successfully.");
+
+            // Read the output.
+            let public_values = decode_public_values(output.as_slice())?;
+            print_public_values(&public_values);
+            check_public_values(&public_values, &expected)?;
+            println!("Values are correct!");
+
+            // Record the number of cycles executed.
+            println!("Number of cycles: {}", report.total_instruction_count());
+        }
+        Command::Prove {
+            resize,
+            output_proof,
+            output_image,
+            compressed,
+        } => {
+            let (image, dst_w, dst_h) = load(&resize)?;
+            let (output, expected) = resize_natively(&image, dst_w, dst_h)?;
+
+            // Setup the prover client and the program for proving.
+            let client = args.prover.client();
+            let (pk, vk) = client.setup(RESIZE_ELF);
+
+            // Generate the proof
+            let prove = client.prove(&pk, program_input(&image, dst_w, dst_h));
+            let proof = if compressed {
+                prove.compressed().run()
+            } else {
+                prove.run()
+            }
+            .context("failed to generate proof")?;
+            println!("Successfully generated proof!");
+
+            // Verify the proof.
+            client
+                .verify(&proof, &vk)
+                .context("failed to verify proof")?;
+            println!("Successfully verified proof!");
+
+            let public_values = decode_public_values(proof.public_values.as_slice())?;
+            print_public_values(&public_values);
+            check_public_values(&public_values, &expected)?;
+
+            save_proof(&output_proof, &proof)?;
+            println!("Proof written to {}", output_proof.display());
+            let output_image = output_image.unwrap_or_else(|| output_proof.with_extension("png"));
+            save_image(&output_image, &output)?;
+            println!("Resized image written to {}", output_image.display());
+        }
+        Command::ProveDir {
+            input_dir,
+            output_dir,
+            size,
+            jobs,
+            compressed,
+        } => {
+            let frames = batch::frame_paths(&input_dir)?;
+            fs::create_dir_all(&output_dir)
+                .with_context(|| format!("cannot create the directory {}", output_dir.display()))?;
+            // The resized frames would be taken for frames on resume
+            ensure!(
+                input_dir.canonicalize()? != output_dir.canonicalize()?,
+                "the output directory must not be the directory of the frames"
+            );
+            println!("Proving {} frames of {}", frames.len(), input_dir.display());
+
+            // Setup the prover client and the program for proving.
+            let client = args.prover.client();
+            let (pk, vk) = client.setup(RESIZE_ELF);
+
+            let settings = BatchSettings {
+                width: size.width,
+                height: size.height,
+                scale: size.scale,
+                compressed,
+                vkey_hash: vk.bytes32(),
+            };
+            let checkpoint_path = output_dir.join(CHECKPOINT_FILE);
+            let mut checkpoint = Checkpoint::load_or_new(&checkpoint_path, settings)?;
+            let frame_prover = FrameProver {
+                client: &client,
+                pk: &pk,
+                vk: &vk,
+                size,
+                compressed,
+                output_dir: &output_dir,
+            };
+            let outcome =
+                batch::prove_frames(&frames, jobs, &mut checkpoint, &checkpoint_path, |frame| {
+                    frame_prover.prove(frame)
+                })?;
+            println!(
+                "Proved {} frames, {} were already proved",
+                outcome.proved, outcome.resumed
+            );
+            for (frame, err) in &outcome.failed {
+                println!("Failed to prove {}: {:#}", frame, err);
+            }
+            ensure!(
+                outcome.failed.is_empty(),
+                "{} frames failed, run the command again to retry them",
+                outcome.failed.len()
+            );
+
+            let manifest_path = output_dir.join(MANIFEST_FILE);
+            BatchManifest::new(&checkpoint, client.version(), bundle::elf_hash())
+                .save(&manifest_path)?;
+            println!("Manifest written to {}", manifest_path.display());
+        }
+        Command::Pack {
+            proof,
+            image,
+            output_dir,
+            registry,
+        } => {
+            let registry = Registry::load(&registry)?;
+            let proof = load_proof(&proof)?;
+            let image = image.as_deref().map(load_image).transpose()?;
+
+            // Verify the proof before bundling it, with the key of the program if it is pinned.
+            let client = args.prover.client();
+            let (_, vk) = client.setup(RESIZE_ELF);
+            let program = registry.verify(&client, &proof, &vk.bytes32())?;
+            println!("Program version: {}", program.version);
+
+            let path = Bundle::new(&proof, &vk, image.as_ref())?.write(&output_dir)?;
+            println!("Bundle written to {}", path.display());
+        }
+        Command::Unpack { bundle, output_dir } => {
+            let (bundle, id) = Bundle::read(&bundle)?;
+            println!("Bundle: {}", id);
+            fs::create_dir_all(&output_dir)
+                .with_context(|| format!("cannot create the directory {}", output_dir.display()))?;
+            let proof_path = output_dir.join("proof.bin");
+            save_proof(&proof_path, &bundle.proof()?)?;
+            println!("Proof written to {}", proof_path.display());
+            if let Some(image) = bundle.output_image()? {
+                let image_path = output_dir.join("output.png");
+                save_image(&image_path, &image)?;
+                println!("Resized image written to {}", image_path.display());
+            }
+        }
+        Command::Inspect { bundle } => {
+            let (bundle, id) = Bundle::read(&bundle)?;
+            let proof = bundle.proof()?;
+            println!("Bundle: {}", id);
+            println!("Format version: {}", bundle.version);
+            println!("SP1 version: {}", bundle.sp1_version);
+            let elf_hash = hex::encode(bundle.elf_hash);
+            if bundle.elf_hash == bundle::elf_hash() {
+                println!("Program ELF hash: 0x{} (this program)", elf_hash);
+            } else {
+                println!("Program ELF hash: 0x{} (not this program)", elf_hash);
+            }
+            println!("Verification key hash: {}", bundle.vkey_hash);
+            println!(
+                "Proof: {}, {} bytes",
+                proof_kind(&proof.proof),
+                bundle.proof.len()
+            );
+            print_public_values(&decode_public_values(proof.public_values.as_slice())?);
+            match bundle.output_image()? {
+                Some(image) => println!("Resized image: {}x{}", image.width(), image.height()),
+                None => println!("Resized image: none"),
+            }
+        }
+        Command::Embed {
+            bundle,
+            image,
+            output,
+        } => {
+            let (mut bundle, _) = Bundle::read(&bundle)?;
+            let public_values = decode_public_values(bundle.proof()?.public_values.as_slice())?;
+            check_output_image(&public_values, &load_image(&image)?)?;
+
+            // The image itself is the resized image
+            bundle.output_image = None;
+            let bytes = bundle.to_bytes()?;
+            let file =
+                fs::read(&image).with_context(|| format!("cannot read {}", image.display()))?;
+            fs::write(&output, embed::embed(&file, &bytes)?)
+                .with_context(|| format!("cannot write {}", output.display()))?;
+            println!(
+                "Bundle {} embedded in {}",
+                bundle_id(&bytes),
+                output.display()
+            );
+        }
+        Command::Extract { image, output_dir } => {
+            let file =
+                fs::read(&image).with_context(|| format!("cannot read {}", image.display()))?;
+            let bytes = embed::extract(&file)?
+                .with_context(|| format!("{} has no embedded bundle", image.display()))?;
+            let path = Bundle::from_bytes(&bytes)?.write(&output_dir)?;
+            println!("Bundle written to {}", path.display());
+        }
+        Command::Manifest {
+            bundle,
+            output,
+            title,
+            format,
+            source_title,
+            registry,
+        } => {
+            let registry = Registry::load(&registry)?;
+            let (bundle, _) = Bundle::read(&bundle)?;
+
+            // Only a verified proof of a pinned program is carried by the manifest.
+            let client = args.prover.client();
+            let manifest =
+                Manifest::new(&bundle, &client, &registry, &title, &format, &source_title)?;
+            fs::write(&output, serde_json::to_string_pretty(&manifest)?)
+                .with_context(|| format!("cannot write {}", output.display()))?;
+            println!("Manifest written to {}", output.display());
+        }
+    }
+    Ok(())
+}
+
+/// Proves the frames of `prove-dir`.
+struct FrameProver<'a> {
+    client: &'a ProverClient,
+    pk: &'a SP1ProvingKey,
+    vk: &'a SP1VerifyingKey,
+    size: SizeArgs,
+    compressed: bool,
+    output_dir: &'a Path,
+}
+
+impl FrameProver<'_> {
+    /// Proves a frame, and writes its proof and the resized frame next to each other.
+    fn prove(&self, frame: &Path) -> Result<FrameProof> {
+        let name = batch::frame_name(frame)?;
+        let image = load_image(frame)?;
+        let (dst_w, dst_h) = self.size.output_dimensions(image.width(), image.height())?;
+        let (output, expected) = resize_natively(&image, dst_w, dst_h)?;
+
+        let prove = self
+            .client
+            .prove(self.pk, program_input(&image, dst_w, dst_h));
+        let proof = if self.compressed {
+            prove.compressed().run()
+        } else {
+            prove.run()
+        }
+        .with_context(|| format!("failed to generate the proof of {}", name))?;
+        self.client
+            .verify(&proof, self.vk)
+            .with_context(|| format!("failed to verify the proof of {}", name))?;
+        let public_values = decode_public_values(proof.public_values.as_slice())?;
+        check_public_values(&public_values, &expected)?;
+
+        let stem = Path::new(&name).with_extension("");
+        let proof_path = stem.with_extension("bin");
+        let image_path = stem.with_extension("png");
+        save_proof(&self.output_dir.join(&proof_path), &proof)?;
+        save_image(&self.output_dir.join(&image_path), &output)?;
+        println!("Proved {}", name);
+        Ok(FrameProof {
+            frame: name,
+            proof: proof_path,
+            output_image: image_path,
+            public_values: AttestedResize::from(&public_values),
+        })
     }
+}
 
-    // Setup the prover client.
-    let client = ProverClient::new();
-
-    // Setup the inputs.
-    let mut stdin = SP1Stdin::new();
-    stdin.write(&args.n);
-
-    println!("n: {}", args.n);
-
-    if args.execute {
-        // Execute the program
-        let (output, report) = client.execute(FIBONACCI_ELF, stdin).run().unwrap();
-        println!("Program executed successfully.");
-
-        // Read the output.
-        let decoded = PublicValuesStruct::abi_decode(output.as_slice(), true).unwrap();
-        let PublicValuesStruct { n, a, b } = decoded;
-        println!("n: {}", n);
-        println!("a: {}", a);
-        println!("b: {}", b);
-
-        let (expected_a, expected_b) = fibonacci_lib::fibonacci(n);
-        assert_eq!(a, expected_a);
-        assert_eq!(b, expected_b);
-        println!("Values are correct!");
-
-        // Record the number of cycles executed.
-        println!("Number of cycles: {}", report.total_instruction_count());
-    } else {
-        // Setup the program for proving.
-        let (pk, vk) = client.setup(FIBONACCI_ELF);
-
-        // Generate the proof
-        let proof = client
-            .prove(&pk, stdin)
-            .run()
-            .expect("failed to generate proof");
-
-        println!("Successfully generated proof!");
-
-        // Verify the proof.
-        client.verify(&proof, &vk).expect("failed to verify proof");
-        println!("Successfully verified proof!");
+fn proof_kind(proof: &SP1Proof) -> &'static str {
+    match proof {
+        SP1Proof::Core(_) => "core",
+        SP1Proof::Compressed(_) => "compressed",
+        SP1Proof::Plonk(_) => "PLONK",
+        SP1Proof::Groth16(_) => "Groth16",
     }
 }
+
+/// Loads the source image and computes the dimensions of the resized image.
+fn load(resize: &ResizeArgs) -> Result<(GrayImage, u32, u32)> {
+    let image = load_image(&resize.input)?;
+    let (dst_w, dst_h) = resize.output_dimensions(image.width(), image.height())?;
+    println!(
+        "Resizing {} from {}x{} to {}x{}",
+        resize.input.display(),
+        image.width(),
+        image.height(),
+        dst_w,
+        dst_h
+    );
+    Ok((image, dst_w, dst_h))
+}
+
+/// Resizes the image outside of the zkVM, returning the resized image and the expected public
+/// values.
+fn resize_natively(
+    image: &GrayImage,
+    dst_w: u32,
+    dst_h: u32,
+) -> Result<(GrayImage, PublicValuesStruct)> {
+    let (pixels, public_values) =
+        attest_resize(image.as_raw(), image.width(), image.height(), dst_w, dst_h)
+            .context("invalid image dimensions")?;
+    let output = GrayImage::from_raw(dst_w, dst_h, pixels).context("invalid resized image")?;
+    Ok((output, public_values))
+}
+
+fn check_public_values(actual: &PublicValuesStruct, expected: &PublicValuesStruct) -> Result<()> {
+    ensure!(
+        PublicValuesStruct::abi_encode(actual) == PublicValuesStruct::abi_encode(expected),
+        "the public values of the program differ from those of a native run"
+    );
+    Ok(())
+}
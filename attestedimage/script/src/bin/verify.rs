@@ -0,0 +1,128 @@
+//! A script to check that a saved proof attests to a published image: it verifies the proof with
+//! the verification key of a pinned program, and compares the image with the hash and dimensions
+//! of the resized image in the public values.
+//!
+//! You can run this script using the following command:
+//! ```shell
+//! RUST_LOG=info cargo run --release --bin verify -- --proof proof.bin --image proof.png
+//! ```
+//! or, for an image with an embedded bundle:
+//! ```shell
+//! RUST_LOG=info cargo run --release --bin verify -- --image attested.png
+//! ```
+//!
+//! The proof is verified with the key of the pinned program it is for: the one named by the bundle,
+//! or for a bare proof, the latest pinned program whose key verifies it. The script exits with a
+//! nonzero code if the program isn't pinned in the registry, the proof is invalid or the images
+//! don't match. Since the hash is over the exact pixels, the resized image must have been saved
+//! losslessly, e.g. as PNG.
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use fibonacci_script::bundle::{bundle_id, Bundle};
+use fibonacci_script::embed;
+use fibonacci_script::registry::{Registry, DEFAULT_REGISTRY};
+use fibonacci_script::{
+    check_output_image, check_source_image, decode_public_values, load_image, load_proof,
+    print_public_values, ProverArgs,
+};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+/// The arguments for the verify command.
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct VerifyArgs {
+    /// The proof with its public values, as written by `prove`. By default, the proof of the
+    /// bundle embedded in the image.
+    #[arg(long)]
+    proof: Option<PathBuf>,
+    /// The resized image to check against the proof.
+    #[arg(long)]
+    image: PathBuf,
+    /// Also check the source image against the proof.
+    #[arg(long)]
+    source: Option<PathBuf>,
+    /// The registry of pinned programs, whose verification keys are trusted.
+    #[arg(long, default_value = DEFAULT_REGISTRY)]
+    registry: PathBuf,
+
+    #[command(flatten)]
+    prover: ProverArgs,
+}
+
+fn main() -> Result<ExitCode> {
+    // Setup the logger.
+    sp1_sdk::utils::setup_logger();
+
+    // Parse the command line arguments.
+    let args = VerifyArgs::parse();
+
+    let registry = Registry::load(&args.registry)?;
+    let image = load_image(&args.image)?;
+    let source = args.source.as_deref().map(load_image).transpose()?;
+    let (proof, bundle) = match &args.proof {
+        Some(path) => (load_proof(path)?, None),
+        None => {
+            let bundle = embedded_bundle(&args.image)?;
+            (bundle.proof()?, Some(bundle))
+        }
+    };
+
+    // Verify the proof with the key of the pinned program it is for. A bundle names the program,
+    // a bare proof is tried with the key of every pinned program.
+    let client = args.prover.client();
+    let verified = match &bundle {
+        Some(bundle) => {
+            if let Err(err) = registry.check(&bundle.vkey_hash) {
+                println!("Untrusted program: {}", err);
+                return Ok(ExitCode::FAILURE);
+            }
+            registry.verify(&client, &proof, &bundle.vkey_hash)
+        }
+        None => registry.verify_any(&client, &proof),
+    };
+    match verified {
+        Ok(program) => println!("Program version: {}", program.version),
+        Err(err) => {
+            println!("Invalid proof: {:#}", err);
+            return Ok(ExitCode::FAILURE);
+        }
+    }
+    println!("Successfully verified proof!");
+
+    // Compare the images with the public values.
+    let public_values = decode_public_values(proof.public_values.as_slice())?;
+    print_public_values(&public_values);
+    let mut checks = vec![check_output_image(&public_values, &image)];
+    if let Some(source) = &source {
+        checks.push(check_source_image(&public_values, source));
+    }
+    let mut matches = true;
+    for check in checks {
+        if let Err(err) = check {
+            println!("Mismatch: {}", err);
+            matches = false;
+        }
+    }
+    if !matches {
+        return Ok(ExitCode::FAILURE);
+    }
+    println!("The images match the proof.");
+    Ok(ExitCode::SUCCESS)
+}
+
+/// The bundle embedded in the metadata of an image.
+fn embedded_bundle(path: &Path) -> Result<Bundle> {
+    let file = fs::read(path).with_context(|| format!("cannot read {}", path.display()))?;
+    let bytes = embed::extract(&file)?.with_context(|| {
+        format!(
+            "{} has no embedded bundle, pass the proof with --proof",
+            path.display()
+        )
+    })?;
+    let bundle = Bundle::from_bytes(&bytes)?;
+    println!("Embedded bundle: {}", bundle_id(&bytes));
+    Ok(bundle)
+}
@@ -1,27 +1,69 @@
-//! A script to print the program verification key.
+//! A script to print the program verification key, and pin it in the registry of trusted
+//! programs.
 //!
 //! You can run this script using the following command:
 //! ```shell
 //! RUST_LOG=info cargo run --bin vkey --release
 //! ```
+//! or, to pin the program as the version 1.0.0:
+//! ```shell
+//! RUST_LOG=info cargo run --bin vkey --release -- --pin 1.0.0
+//! ```
+
+use anyhow::Result;
+use clap::Parser;
+use fibonacci_script::bundle::elf_hash;
+use fibonacci_script::registry::{PinnedProgram, Registry, DEFAULT_REGISTRY};
+use fibonacci_script::{ProverArgs, RESIZE_ELF};
+use sp1_sdk::HashableKey;
+use std::path::PathBuf;
 
-use sp1_sdk::{HashableKey, ProverClient};
+/// The arguments for the vkey command.
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct VKeyArgs {
+    /// Pin the program in the registry under this version.
+    #[arg(long)]
+    pin: Option<String>,
+    /// The registry of pinned programs.
+    #[arg(long, default_value = DEFAULT_REGISTRY)]
+    registry: PathBuf,
 
-/// The ELF (executable and linkable format) file for the Succinct RISC-V zkVM.
-///
-/// This file is generated by running `cargo prove build` inside the `program` directory.
-pub const FIBONACCI_ELF: &[u8] = include_bytes!("../../../elf/riscv32im-succinct-zkvm-elf");
+    #[command(flatten)]
+    prover: ProverArgs,
+}
 
-fn main() {
+fn main() -> Result<()> {
     // Setup the logger.
     sp1_sdk::utils::setup_logger();
 
+    // Parse the command line arguments.
+    let args = VKeyArgs::parse();
+    let mut registry = Registry::load_or_default(&args.registry)?;
+
     // Setup the prover client.
-    let client = ProverClient::new();
+    let client = args.prover.client();
 
     // Setup the program.
-    let (_, vk) = client.setup(FIBONACCI_ELF);
+    let (_, vk) = client.setup(RESIZE_ELF);
 
     // Print the verification key.
     println!("Program Verification Key: {}", vk.bytes32());
+    println!("Program ELF hash: 0x{}", hex::encode(elf_hash()));
+
+    if let Some(version) = args.pin {
+        registry.pin(PinnedProgram::new(&version, &vk)?)?;
+        registry.save(&args.registry)?;
+        println!(
+            "Pinned as the version {} in {}",
+            version,
+            args.registry.display()
+        );
+    } else {
+        match registry.find(&vk.bytes32()) {
+            Some(program) => println!("Pinned as the version {}", program.version),
+            None => println!("Not pinned in {}", args.registry.display()),
+        }
+    }
+    Ok(())
 }
@@ -0,0 +1,189 @@
+//! A single file holding an attestation: the proof with its public values, what identifies the
+//! program that produced it, and optionally the resized image.
+//!
+//! A bundle is a CBOR map. It is content-addressed: its id is the SHA-256 digest of its bytes, and
+//! `Bundle::write` names the file after it, so a renamed or corrupted bundle is detected on read.
+
+use crate::{check_output_image, decode_public_values, RESIZE_ELF};
+use anyhow::{ensure, Context, Result};
+use fibonacci_lib::PublicValuesStruct;
+use image::{GrayImage, ImageFormat};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sp1_sdk::{HashableKey, SP1ProofWithPublicValues, SP1VerifyingKey};
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+/// The version of the bundle format, bumped on incompatible changes.
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// The extension of bundle files.
+pub const BUNDLE_EXTENSION: &str = "bundle";
+
+/// An attestation bundle.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Bundle {
+    /// The version of the bundle format.
+    pub version: u32,
+    /// The version of SP1 the proof was generated with.
+    pub sp1_version: String,
+    /// The SHA-256 digest of the program ELF.
+    #[serde(with = "serde_bytes")]
+    pub elf_hash: [u8; 32],
+    /// The hash of the program verification key, as the EVM verifier expects it.
+    pub vkey_hash: String,
+    /// The public values of the proof, decoded.
+    pub public_values: AttestedResize,
+    /// The proof with its public values, encoded with bincode.
+    #[serde(with = "serde_bytes")]
+    pub proof: Vec<u8>,
+    /// The resized image, encoded as PNG.
+    #[serde(with = "serde_bytes", default, skip_serializing_if = "Option::is_none")]
+    pub output_image: Option<Vec<u8>>,
+}
+
+/// The public values committed by the program, see `PublicValuesStruct`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AttestedResize {
+    #[serde(with = "serde_bytes")]
+    pub source_hash: [u8; 32],
+    #[serde(with = "serde_bytes")]
+    pub output_hash: [u8; 32],
+    pub source_width: u32,
+    pub source_height: u32,
+    pub output_width: u32,
+    pub output_height: u32,
+    pub transform_id: u32,
+}
+
+impl From<&PublicValuesStruct> for AttestedResize {
+    fn from(public_values: &PublicValuesStruct) -> Self {
+        AttestedResize {
+            source_hash: public_values.sourceHash.0,
+            output_hash: public_values.outputHash.0,
+            source_width: public_values.sourceWidth,
+            source_height: public_values.sourceHeight,
+            output_width: public_values.outputWidth,
+            output_height: public_values.outputHeight,
+            transform_id: public_values.transformId,
+        }
+    }
+}
+
+/// The id of the bundle encoded as `bytes`.
+pub fn bundle_id(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+/// The SHA-256 digest of the program ELF.
+pub fn elf_hash() -> [u8; 32] {
+    Sha256::digest(RESIZE_ELF).into()
+}
+
+impl Bundle {
+    /// Bundles a proof of this program verified with `vk`, and optionally the resized image it
+    /// attests to.
+    pub fn new(
+        proof: &SP1ProofWithPublicValues,
+        vk: &SP1VerifyingKey,
+        output_image: Option<&GrayImage>,
+    ) -> Result<Self> {
+        let public_values = decode_public_values(proof.public_values.as_slice())?;
+        let output_image = output_image
+            .map(|image| -> Result<Vec<u8>> {
+                check_output_image(&public_values, image)?;
+                let mut png = Vec::new();
+                image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+                Ok(png)
+            })
+            .transpose()?;
+        Ok(Bundle {
+            version: BUNDLE_VERSION,
+            sp1_version: proof.sp1_version.clone(),
+            elf_hash: elf_hash(),
+            vkey_hash: vk.bytes32(),
+            public_values: AttestedResize::from(&public_values),
+            proof: bincode::serialize(proof)?,
+            output_image,
+        })
+    }
+
+    /// Encodes the bundle.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        ciborium::into_writer(self, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Decodes a bundle, checking that its parts are consistent with each other.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let bundle: Bundle = ciborium::from_reader(bytes).context("not a bundle")?;
+        ensure!(
+            bundle.version == BUNDLE_VERSION,
+            "unsupported bundle version {}, expected {}",
+            bundle.version,
+            BUNDLE_VERSION
+        );
+        let proof = bundle.proof()?;
+        let public_values = decode_public_values(proof.public_values.as_slice())?;
+        ensure!(
+            AttestedResize::from(&public_values) == bundle.public_values,
+            "the public values of the bundle differ from those of its proof"
+        );
+        if let Some(image) = bundle.output_image()? {
+            check_output_image(&public_values, &image)?;
+        }
+        Ok(bundle)
+    }
+
+    /// Writes the bundle in `dir`, to a file named after its id, and returns its path.
+    pub fn write(&self, dir: &Path) -> Result<PathBuf> {
+        let bytes = self.to_bytes()?;
+        fs::create_dir_all(dir)
+            .with_context(|| format!("cannot create the directory {}", dir.display()))?;
+        let path = dir.join(bundle_id(&bytes)).with_extension(BUNDLE_EXTENSION);
+        fs::write(&path, bytes).with_context(|| format!("cannot write {}", path.display()))?;
+        Ok(path)
+    }
+
+    /// Reads a bundle and returns it with its id. If the file is named after an id, as written by
+    /// `write`, it must be the id of its contents.
+    pub fn read(path: &Path) -> Result<(Self, String)> {
+        let bytes = fs::read(path).with_context(|| format!("cannot read {}", path.display()))?;
+        let id = bundle_id(&bytes);
+        if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+            ensure!(
+                !is_id(name) || name == id,
+                "{} is named after the bundle {}, but its contents are the bundle {}",
+                path.display(),
+                name,
+                id
+            );
+        }
+        let bundle = Self::from_bytes(&bytes)
+            .with_context(|| format!("invalid bundle {}", path.display()))?;
+        Ok((bundle, id))
+    }
+
+    /// The proof with its public values.
+    pub fn proof(&self) -> Result<SP1ProofWithPublicValues> {
+        bincode::deserialize(&self.proof).context("invalid proof in the bundle")
+    }
+
+    /// The resized image, if the bundle has one.
+    pub fn output_image(&self) -> Result<Option<GrayImage>> {
+        self.output_image
+            .as_ref()
+            .map(|png| -> Result<GrayImage> {
+                let image = image::load_from_memory_with_format(png, ImageFormat::Png)
+                    .context("invalid image in the bundle")?;
+                Ok(image.into_luma8())
+            })
+            .transpose()
+    }
+}
+
+fn is_id(name: &str) -> bool {
+    name.len() == 64 && name.bytes().all(|b| b.is_ascii_hexdigit())
+}
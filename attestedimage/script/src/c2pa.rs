@@ -0,0 +1,220 @@
+//! C2PA-style manifests of attested edits, for consumers that read provenance as C2PA rather than
+//! as SP1 proofs.
+//!
+//! The manifest follows the JSON manifest definition of the C2PA tools: the source image is the
+//! `parentOf` ingredient, a `c2pa.actions` assertion lists the edit with its parameters, and a
+//! custom assertion carries the SP1 proof and its public values. Everything is derived from a
+//! bundle, so the actions and the ingredient hash are those the program committed to, and the
+//! manifest is only written once the proof is verified with the key of a pinned program.
+//!
+//! The manifest isn't signed and has no hard binding to the file: the output hash of the proof
+//! binds it to the pixels of the edited image.
+
+use crate::bundle::{AttestedResize, Bundle};
+use crate::decode_public_values;
+use crate::registry::Registry;
+use anyhow::{bail, ensure, Context, Result};
+use base64::prelude::{Engine, BASE64_STANDARD};
+use fibonacci_lib::TRANSFORM_RESIZE;
+use serde::{Deserialize, Serialize};
+use sp1_sdk::{ProverClient, SP1ProofWithPublicValues};
+
+/// The label of the assertion carrying the SP1 proof.
+pub const SP1_ASSERTION_LABEL: &str = "org.attestedimage.sp1-proof";
+
+/// The product writing the manifests, as `name/version`.
+pub const CLAIM_GENERATOR: &str = concat!("attestedimage/", env!("CARGO_PKG_VERSION"));
+
+/// The hash algorithm of the hashes in the manifest.
+const HASH_ALGORITHM: &str = "sha256";
+
+/// A C2PA-style manifest.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    pub claim_generator: String,
+    pub title: String,
+    pub format: String,
+    pub ingredients: Vec<Ingredient>,
+    pub assertions: Vec<Assertion>,
+}
+
+/// An asset the edited image was made from.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Ingredient {
+    pub title: String,
+    pub relationship: String,
+    pub hash: PixelHash,
+}
+
+/// The SHA-256 hash of the row-major 8-bit grayscale pixels of an image, see
+/// `fibonacci_lib::image_hash`. C2PA hashes files, but the program only sees the pixels.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PixelHash {
+    pub alg: String,
+    /// The hash, base64 encoded.
+    pub hash: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// A statement about the edited image, labelled as in the C2PA specification.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "label", content = "data")]
+pub enum Assertion {
+    #[serde(rename = "c2pa.actions")]
+    Actions { actions: Vec<Action> },
+    #[serde(rename = "org.attestedimage.sp1-proof")]
+    Sp1Proof(Sp1ProofAssertion),
+}
+
+/// An edit of the source image.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Action {
+    pub action: String,
+    #[serde(rename = "softwareAgent")]
+    pub software_agent: String,
+    pub parameters: ActionParameters,
+}
+
+/// The parameters of an edit, as committed by the program.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ActionParameters {
+    pub width: u32,
+    pub height: u32,
+    #[serde(rename = "transformId")]
+    pub transform_id: u32,
+}
+
+/// The SP1 proof that the edited image is the output of the actions on the ingredient.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Sp1ProofAssertion {
+    pub sp1_version: String,
+    /// The version of the program in the registry.
+    pub program_version: String,
+    /// The SHA-256 digest of the program ELF, hex encoded.
+    pub elf_hash: String,
+    pub vkey_hash: String,
+    /// The hash of the pixels of the edited image.
+    pub output_hash: PixelHash,
+    /// The ABI-encoded public values, hex encoded.
+    pub public_values: String,
+    /// The proof with its public values encoded with bincode, base64 encoded.
+    pub proof: String,
+}
+
+/// The C2PA action of a `transformId`.
+fn action_name(transform_id: u32) -> Result<&'static str> {
+    match transform_id {
+        TRANSFORM_RESIZE => Ok("c2pa.resized"),
+        _ => bail!("no C2PA action for the transform {}", transform_id),
+    }
+}
+
+impl Manifest {
+    /// The manifest of the edit attested by `bundle`, producing the image `title` of MIME type
+    /// `format` from the image `source_title`. The proof of the bundle must verify with the key of
+    /// its program, which must be pinned in `registry`.
+    pub fn new(
+        bundle: &Bundle,
+        client: &ProverClient,
+        registry: &Registry,
+        title: &str,
+        format: &str,
+        source_title: &str,
+    ) -> Result<Self> {
+        let proof = bundle.proof()?;
+        let program = registry.verify(client, &proof, &bundle.vkey_hash)?;
+        let public_values =
+            AttestedResize::from(&decode_public_values(proof.public_values.as_slice())?);
+        Ok(Manifest {
+            claim_generator: CLAIM_GENERATOR.to_string(),
+            title: title.to_string(),
+            format: format.to_string(),
+            ingredients: vec![Ingredient {
+                title: source_title.to_string(),
+                relationship: "parentOf".to_string(),
+                hash: PixelHash::new(
+                    &public_values.source_hash,
+                    public_values.source_width,
+                    public_values.source_height,
+                ),
+            }],
+            assertions: vec![
+                Assertion::Actions {
+                    actions: vec![Action {
+                        action: action_name(public_values.transform_id)?.to_string(),
+                        software_agent: CLAIM_GENERATOR.to_string(),
+                        parameters: ActionParameters {
+                            width: public_values.output_width,
+                            height: public_values.output_height,
+                            transform_id: public_values.transform_id,
+                        },
+                    }],
+                },
+                Assertion::Sp1Proof(Sp1ProofAssertion {
+                    sp1_version: bundle.sp1_version.clone(),
+                    program_version: program.version.clone(),
+                    elf_hash: format!("0x{}", hex::encode(bundle.elf_hash)),
+                    vkey_hash: bundle.vkey_hash.clone(),
+                    output_hash: PixelHash::new(
+                        &public_values.output_hash,
+                        public_values.output_width,
+                        public_values.output_height,
+                    ),
+                    public_values: format!("0x{}", hex::encode(proof.public_values.as_slice())),
+                    proof: BASE64_STANDARD.encode(&bundle.proof),
+                }),
+            ],
+        })
+    }
+
+    /// The SP1 proof assertion of the manifest.
+    pub fn sp1_proof(&self) -> Result<&Sp1ProofAssertion> {
+        let mut proofs = self
+            .assertions
+            .iter()
+            .filter_map(|assertion| match assertion {
+                Assertion::Sp1Proof(proof) => Some(proof),
+                _ => None,
+            });
+        let proof = proofs
+            .next()
+            .with_context(|| format!("the manifest has no {} assertion", SP1_ASSERTION_LABEL))?;
+        ensure!(
+            proofs.next().is_none(),
+            "the manifest has several {} assertions",
+            SP1_ASSERTION_LABEL
+        );
+        Ok(proof)
+    }
+
+    /// The SP1 proof with its public values carried by the manifest.
+    pub fn proof(&self) -> Result<SP1ProofWithPublicValues> {
+        let bytes = BASE64_STANDARD
+            .decode(&self.sp1_proof()?.proof)
+            .context("invalid proof encoding in the manifest")?;
+        bincode::deserialize(&bytes).context("invalid proof in the manifest")
+    }
+}
+
+impl PixelHash {
+    fn new(hash: &[u8; 32], width: u32, height: u32) -> Self {
+        PixelHash {
+            alg: HASH_ALGORITHM.to_string(),
+            hash: BASE64_STANDARD.encode(hash),
+            width,
+            height,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_action_name() {
+        assert_eq!(action_name(TRANSFORM_RESIZE).unwrap(), "c2pa.resized");
+        assert!(action_name(7).is_err());
+    }
+}
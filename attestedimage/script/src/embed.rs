@@ -0,0 +1,311 @@
+//! Embedding bundles in the metadata of the images they attest to, so that a published image
+//! carries its own proof.
+//!
+//! In a PNG file the bundle is a private ancillary chunk, in a JPEG file a sequence of APP11
+//! segments. Decoders skip both, and the public values hash the decoded pixels, so embedding a
+//! bundle doesn't change what it attests to.
+//!
+//! The pixels must be exactly the proven ones though. JPEG is lossy: a JPEG file only attests if
+//! these very bytes decode to the resized pixels, which encoding the resized image generally doesn't
+//! give, and re-encoding or decoding it with another decoder may not preserve. Only the bytes the
+//! bundle was checked against at embedding carry the attestation.
+
+use anyhow::{bail, ensure, Context, Result};
+
+/// The type of the PNG chunk holding a bundle: ancillary, private, and not safe to copy since it
+/// depends on the pixels.
+pub const PNG_CHUNK_TYPE: [u8; 4] = *b"zkPF";
+
+/// The identifier starting the APP11 segments holding a bundle.
+pub const JPEG_IDENTIFIER: &[u8] = b"SP1-ATTESTATION\0";
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+const PNG_IEND: [u8; 4] = *b"IEND";
+
+const JPEG_SOI: u8 = 0xd8;
+const JPEG_EOI: u8 = 0xd9;
+const JPEG_SOS: u8 = 0xda;
+const JPEG_APP0: u8 = 0xe0;
+const JPEG_APP11: u8 = 0xeb;
+const JPEG_APP15: u8 = 0xef;
+
+/// The payload of an APP11 segment: the identifier, the index of the segment and the number of
+/// segments as big-endian u32, then a part of the bundle.
+const JPEG_HEADER_LEN: usize = JPEG_IDENTIFIER.len() + 8;
+const JPEG_MAX_PART_LEN: usize = u16::MAX as usize - 2 - JPEG_HEADER_LEN;
+
+/// Returns the image file `image` with `bundle` embedded, replacing any bundle it had.
+pub fn embed(image: &[u8], bundle: &[u8]) -> Result<Vec<u8>> {
+    if image.starts_with(PNG_SIGNATURE) {
+        embed_png(image, bundle)
+    } else if image.starts_with(&[0xff, JPEG_SOI]) {
+        embed_jpeg(image, bundle)
+    } else {
+        bail!("only PNG and JPEG images can embed a bundle")
+    }
+}
+
+/// Returns the bundle embedded in the image file `image`, if any.
+pub fn extract(image: &[u8]) -> Result<Option<Vec<u8>>> {
+    if image.starts_with(PNG_SIGNATURE) {
+        extract_png(image)
+    } else if image.starts_with(&[0xff, JPEG_SOI]) {
+        extract_jpeg(image)
+    } else {
+        bail!("only PNG and JPEG images can embed a bundle")
+    }
+}
+
+/// A PNG chunk: its type, its data, and all its bytes including the length and the CRC.
+struct PngChunk<'a> {
+    kind: [u8; 4],
+    data: &'a [u8],
+    raw: &'a [u8],
+}
+
+fn png_chunks(png: &[u8]) -> Result<Vec<PngChunk<'_>>> {
+    let mut chunks = Vec::new();
+    let mut pos = PNG_SIGNATURE.len();
+    loop {
+        ensure!(pos + 8 <= png.len(), "truncated PNG chunk");
+        let len = u32::from_be_bytes(png[pos..pos + 4].try_into()?) as usize;
+        let kind: [u8; 4] = png[pos + 4..pos + 8].try_into()?;
+        ensure!(pos + 12 + len <= png.len(), "truncated PNG chunk");
+        chunks.push(PngChunk {
+            kind,
+            data: &png[pos + 8..pos + 8 + len],
+            raw: &png[pos..pos + 12 + len],
+        });
+        pos += 12 + len;
+        if kind == PNG_IEND {
+            return Ok(chunks);
+        }
+    }
+}
+
+fn png_crc(kind: &[u8; 4], data: &[u8]) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(kind);
+    hasher.update(data);
+    hasher.finalize()
+}
+
+fn embed_png(png: &[u8], bundle: &[u8]) -> Result<Vec<u8>> {
+    let len = u32::try_from(bundle.len())
+        .ok()
+        .filter(|&len| len <= i32::MAX as u32)
+        .context("the bundle is too large for a PNG chunk")?;
+    let mut out = Vec::with_capacity(png.len() + bundle.len() + 12);
+    out.extend_from_slice(PNG_SIGNATURE);
+    for chunk in png_chunks(png)? {
+        if chunk.kind == PNG_CHUNK_TYPE {
+            continue;
+        }
+        // Last, so that decoders reach the pixels without reading through the bundle
+        if chunk.kind == PNG_IEND {
+            out.extend_from_slice(&len.to_be_bytes());
+            out.extend_from_slice(&PNG_CHUNK_TYPE);
+            out.extend_from_slice(bundle);
+            out.extend_from_slice(&png_crc(&PNG_CHUNK_TYPE, bundle).to_be_bytes());
+        }
+        out.extend_from_slice(chunk.raw);
+    }
+    Ok(out)
+}
+
+fn extract_png(png: &[u8]) -> Result<Option<Vec<u8>>> {
+    let mut bundles = png_chunks(png)?
+        .into_iter()
+        .filter(|chunk| chunk.kind == PNG_CHUNK_TYPE);
+    let Some(chunk) = bundles.next() else {
+        return Ok(None);
+    };
+    ensure!(
+        bundles.next().is_none(),
+        "the PNG image embeds several bundles"
+    );
+    let crc = u32::from_be_bytes(chunk.raw[chunk.raw.len() - 4..].try_into()?);
+    ensure!(
+        crc == png_crc(&chunk.kind, chunk.data),
+        "the bundle chunk of the PNG image is corrupted"
+    );
+    Ok(Some(chunk.data.to_vec()))
+}
+
+/// A JPEG segment before the scan: its marker, its data, and all its bytes including the marker
+/// and the length.
+struct JpegSegment<'a> {
+    marker: u8,
+    data: &'a [u8],
+    raw: &'a [u8],
+}
+
+/// Returns the segments between the SOI marker and the first scan, and the offset of the scan.
+fn jpeg_segments(jpeg: &[u8]) -> Result<(Vec<JpegSegment<'_>>, usize)> {
+    let mut segments = Vec::new();
+    let mut pos = 2;
+    loop {
+        ensure!(pos + 2 <= jpeg.len(), "truncated JPEG segment");
+        ensure!(jpeg[pos] == 0xff, "invalid JPEG marker");
+        let marker = jpeg[pos + 1];
+        // Fill bytes
+        if marker == 0xff {
+            pos += 1;
+            continue;
+        }
+        if marker == JPEG_SOS || marker == JPEG_EOI {
+            return Ok((segments, pos));
+        }
+        // Markers without a length: TEM and RST0-7
+        let len = if marker == 0x01 || (0xd0..=0xd7).contains(&marker) {
+            0
+        } else {
+            ensure!(pos + 4 <= jpeg.len(), "truncated JPEG segment");
+            let len = u16::from_be_bytes([jpeg[pos + 2], jpeg[pos + 3]]) as usize;
+            ensure!(
+                len >= 2 && pos + 2 + len <= jpeg.len(),
+                "truncated JPEG segment"
+            );
+            len
+        };
+        segments.push(JpegSegment {
+            marker,
+            data: &jpeg[(pos + 4).min(pos + 2 + len)..pos + 2 + len],
+            raw: &jpeg[pos..pos + 2 + len],
+        });
+        pos += 2 + len;
+    }
+}
+
+fn is_bundle_segment(segment: &JpegSegment) -> bool {
+    segment.marker == JPEG_APP11 && segment.data.starts_with(JPEG_IDENTIFIER)
+}
+
+fn embed_jpeg(jpeg: &[u8], bundle: &[u8]) -> Result<Vec<u8>> {
+    let parts: Vec<&[u8]> = bundle.chunks(JPEG_MAX_PART_LEN).collect();
+    let count = u32::try_from(parts.len()).context("the bundle is too large for a JPEG image")?;
+    let mut bundle_segments =
+        Vec::with_capacity(bundle.len() + parts.len() * (JPEG_HEADER_LEN + 4));
+    for (index, part) in parts.into_iter().enumerate() {
+        bundle_segments.extend_from_slice(&[0xff, JPEG_APP11]);
+        bundle_segments
+            .extend_from_slice(&((2 + JPEG_HEADER_LEN + part.len()) as u16).to_be_bytes());
+        bundle_segments.extend_from_slice(JPEG_IDENTIFIER);
+        bundle_segments.extend_from_slice(&(index as u32).to_be_bytes());
+        bundle_segments.extend_from_slice(&count.to_be_bytes());
+        bundle_segments.extend_from_slice(part);
+    }
+
+    let (segments, scan) = jpeg_segments(jpeg)?;
+    let mut out = Vec::with_capacity(jpeg.len() + bundle_segments.len());
+    out.extend_from_slice(&[0xff, JPEG_SOI]);
+    let mut embedded = false;
+    for segment in segments
+        .iter()
+        .filter(|segment| !is_bundle_segment(segment))
+    {
+        // After the application segments, where readers expect JFIF and Exif
+        if !embedded && !(JPEG_APP0..=JPEG_APP15).contains(&segment.marker) {
+            out.extend_from_slice(&bundle_segments);
+            embedded = true;
+        }
+        out.extend_from_slice(segment.raw);
+    }
+    if !embedded {
+        out.extend_from_slice(&bundle_segments);
+    }
+    out.extend_from_slice(&jpeg[scan..]);
+    Ok(out)
+}
+
+fn extract_jpeg(jpeg: &[u8]) -> Result<Option<Vec<u8>>> {
+    let (segments, _) = jpeg_segments(jpeg)?;
+    let mut bundle = Vec::new();
+    let mut expected = None;
+    for (index, segment) in segments.iter().filter(|s| is_bundle_segment(s)).enumerate() {
+        ensure!(
+            segment.data.len() >= JPEG_HEADER_LEN,
+            "truncated bundle segment in the JPEG image"
+        );
+        let header = &segment.data[JPEG_IDENTIFIER.len()..JPEG_HEADER_LEN];
+        let position = u32::from_be_bytes(header[..4].try_into()?);
+        let count = u32::from_be_bytes(header[4..].try_into()?);
+        ensure!(
+            position as usize == index && *expected.get_or_insert(count) == count,
+            "the bundle segments of the JPEG image are out of order"
+        );
+        bundle.extend_from_slice(&segment.data[JPEG_HEADER_LEN..]);
+    }
+    match expected {
+        None => Ok(None),
+        Some(count) => {
+            let found = segments.iter().filter(|s| is_bundle_segment(s)).count();
+            ensure!(
+                found == count as usize,
+                "the JPEG image has {} of the {} bundle segments",
+                found,
+                count
+            );
+            Ok(Some(bundle))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, ImageFormat, Luma};
+    use std::io::Cursor;
+
+    fn encode(image: &GrayImage, format: ImageFormat) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut bytes), format)
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_embed_and_extract() {
+        let image = GrayImage::from_fn(33, 17, |x, y| Luma([(x * 7 + y * 13) as u8]));
+        // Larger than a JPEG segment
+        let bundle: Vec<u8> = (0..200_000u32).map(|i| (i * 31 % 251) as u8).collect();
+        for format in [ImageFormat::Png, ImageFormat::Jpeg] {
+            let file = encode(&image, format);
+            assert_eq!(extract(&file).unwrap(), None);
+
+            let embedded = embed(&file, &bundle).unwrap();
+            assert_eq!(extract(&embedded).unwrap(), Some(bundle.clone()));
+            // The pixels don't change
+            assert_eq!(
+                image::load_from_memory(&embedded).unwrap().into_luma8(),
+                image::load_from_memory(&file).unwrap().into_luma8()
+            );
+
+            // Embedding again replaces the bundle
+            let replaced = embed(&embedded, b"another bundle").unwrap();
+            assert_eq!(
+                extract(&replaced).unwrap(),
+                Some(b"another bundle".to_vec())
+            );
+        }
+    }
+
+    #[test]
+    fn test_corrupted_png_bundle() {
+        let image = GrayImage::from_pixel(4, 4, Luma([9]));
+        let mut embedded = embed(&encode(&image, ImageFormat::Png), b"bundle").unwrap();
+        let pos = embedded
+            .windows(6)
+            .position(|window| window == b"bundle")
+            .unwrap();
+        embedded[pos] ^= 1;
+        assert!(extract(&embedded).is_err());
+    }
+
+    #[test]
+    fn test_unsupported_format() {
+        assert!(embed(b"GIF89a", b"bundle").is_err());
+        assert!(extract(b"GIF89a").is_err());
+    }
+}
@@ -0,0 +1,257 @@
+//! Host-side helpers shared by the binaries of the script: loading and saving images, preparing the
+//! input of the program and decoding its public values.
+
+pub mod batch;
+pub mod bundle;
+pub mod c2pa;
+pub mod embed;
+pub mod registry;
+
+use alloy_sol_types::SolType;
+use anyhow::{ensure, Context, Result};
+use clap::Args;
+use fibonacci_lib::{image_hash, PublicValuesStruct};
+use image::GrayImage;
+use sp1_sdk::{ProverClient, SP1ProofWithPublicValues, SP1Stdin};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+/// The ELF (executable and linkable format) file for the Succinct RISC-V zkVM.
+///
+/// This file is generated by running `cargo prove build` inside the `program` directory.
+pub const RESIZE_ELF: &[u8] = include_bytes!("../../elf/riscv32im-succinct-zkvm-elf");
+
+/// The image to resize and the size to resize it to.
+#[derive(Args, Debug, Clone)]
+pub struct ResizeArgs {
+    /// The source image: a PNG or JPEG file, converted to grayscale, or a text file with one row
+    /// of 8-bit pixels per line.
+    #[arg(long)]
+    pub input: PathBuf,
+    #[command(flatten)]
+    pub size: SizeArgs,
+}
+
+impl ResizeArgs {
+    /// The dimensions of the resized image, given those of the source image.
+    pub fn output_dimensions(&self, src_w: u32, src_h: u32) -> Result<(u32, u32)> {
+        self.size.output_dimensions(src_w, src_h)
+    }
+}
+
+/// The size to resize images to.
+#[derive(Args, Debug, Clone, Copy)]
+pub struct SizeArgs {
+    /// Width of the resized image.
+    #[arg(long, requires = "height", conflicts_with = "scale")]
+    pub width: Option<u32>,
+    /// Height of the resized image.
+    #[arg(long, requires = "width")]
+    pub height: Option<u32>,
+    /// Scale both dimensions by this factor instead, e.g. 0.25.
+    #[arg(long, required_unless_present = "width")]
+    pub scale: Option<f64>,
+}
+
+impl SizeArgs {
+    /// The dimensions of the resized image, given those of the source image.
+    pub fn output_dimensions(&self, src_w: u32, src_h: u32) -> Result<(u32, u32)> {
+        let (dst_w, dst_h) = match (self.width, self.height, self.scale) {
+            (Some(width), Some(height), _) => (width, height),
+            (_, _, Some(scale)) => {
+                ensure!(scale > 0.0, "the scale must be positive");
+                let scaled = |d: u32| (d as f64 * scale).round().max(1.0) as u32;
+                (scaled(src_w), scaled(src_h))
+            }
+            _ => unreachable!("clap requires --width and --height, or --scale"),
+        };
+        ensure!(
+            dst_w > 0 && dst_h > 0,
+            "the resized image must not be empty"
+        );
+        // scale_image panics if a filter starts before the source
+        let context =
+            fibonacci_lib::Context::new(src_w as i32, src_h as i32, dst_w as i32, dst_h as i32);
+        ensure!(
+            context.map_or(true, |c| c.starts_inside_source()),
+            "the program can't resize {}x{} to {}x{}, the height must be divided by more than 3",
+            src_w,
+            src_h,
+            dst_w,
+            dst_h
+        );
+        Ok((dst_w, dst_h))
+    }
+}
+
+/// The prover generating and verifying the proofs.
+#[derive(Args, Debug, Clone, Copy)]
+pub struct ProverArgs {
+    /// Use the mock prover of SP1: it executes the program, but its proofs are not sound and
+    /// always verify. Without this flag, the `SP1_PROVER` environment variable picks the prover.
+    #[arg(long, global = true)]
+    pub mock: bool,
+}
+
+impl ProverArgs {
+    /// The prover client to use.
+    pub fn client(&self) -> ProverClient {
+        if self.mock {
+            eprintln!("warning: using the mock prover, proofs are not sound");
+            ProverClient::mock()
+        } else {
+            ProverClient::new()
+        }
+    }
+}
+
+/// Loads a grayscale image, see `ResizeArgs::input` for the supported formats.
+pub fn load_image(path: &Path) -> Result<GrayImage> {
+    if is_text(path) {
+        let contents =
+            fs::read_to_string(path).with_context(|| format!("cannot read {}", path.display()))?;
+        let rows = contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .enumerate()
+            .map(|(i, line)| {
+                line.split_whitespace()
+                    .map(|value| {
+                        value.parse::<u8>().with_context(|| {
+                            format!("{}:{}: invalid pixel {:?}", path.display(), i + 1, value)
+                        })
+                    })
+                    .collect::<Result<Vec<u8>>>()
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let width = rows.first().map_or(0, Vec::len);
+        ensure!(width > 0, "{} is empty", path.display());
+        ensure!(
+            rows.iter().all(|row| row.len() == width),
+            "the rows of {} have different lengths",
+            path.display()
+        );
+        let height = rows.len();
+        GrayImage::from_raw(width as u32, height as u32, rows.concat())
+            .context("the image is too large")
+    } else {
+        let image = image::open(path).with_context(|| format!("cannot open {}", path.display()))?;
+        Ok(image.into_luma8())
+    }
+}
+
+/// Saves a grayscale image, as text if `path` ends in `.txt` and in the format of its extension
+/// otherwise.
+pub fn save_image(path: &Path, image: &GrayImage) -> Result<()> {
+    if is_text(path) {
+        let mut contents = String::new();
+        for row in image.as_raw().chunks(image.width() as usize) {
+            let row: Vec<String> = row.iter().map(|pixel| pixel.to_string()).collect();
+            contents.push_str(&row.join(" "));
+            contents.push('\n');
+        }
+        fs::write(path, contents).with_context(|| format!("cannot write {}", path.display()))
+    } else {
+        image
+            .save(path)
+            .with_context(|| format!("cannot write {}", path.display()))
+    }
+}
+
+fn is_text(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "txt")
+}
+
+/// The input of the program resizing `image` to `dst_w` x `dst_h`.
+pub fn program_input(image: &GrayImage, dst_w: u32, dst_h: u32) -> SP1Stdin {
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&image.width());
+    stdin.write(&image.height());
+    stdin.write(&dst_w);
+    stdin.write(&dst_h);
+    stdin.write_vec(image.as_raw().clone());
+    stdin
+}
+
+/// Writes a proof with its public values.
+///
+/// Unlike `SP1ProofWithPublicValues::save`, this doesn't panic if the file cannot be created.
+pub fn save_proof(path: &Path, proof: &SP1ProofWithPublicValues) -> Result<()> {
+    let file = File::create(path).with_context(|| format!("cannot write {}", path.display()))?;
+    bincode::serialize_into(BufWriter::new(file), proof)
+        .with_context(|| format!("cannot write {}", path.display()))
+}
+
+/// Reads a proof with its public values written by `save_proof`.
+pub fn load_proof(path: &Path) -> Result<SP1ProofWithPublicValues> {
+    let file = File::open(path).with_context(|| format!("cannot read {}", path.display()))?;
+    bincode::deserialize_from(BufReader::new(file))
+        .with_context(|| format!("{} is not a proof", path.display()))
+}
+
+/// Decodes the public values committed by the program.
+pub fn decode_public_values(bytes: &[u8]) -> Result<PublicValuesStruct> {
+    PublicValuesStruct::abi_decode(bytes, true).context("invalid public values")
+}
+
+/// Checks that `image` is the resized image the public values attest to.
+pub fn check_output_image(public_values: &PublicValuesStruct, image: &GrayImage) -> Result<()> {
+    check_image(
+        "resized",
+        image,
+        public_values.outputWidth,
+        public_values.outputHeight,
+        public_values.outputHash.0,
+    )
+}
+
+/// Checks that `image` is the source image the public values attest to.
+pub fn check_source_image(public_values: &PublicValuesStruct, image: &GrayImage) -> Result<()> {
+    check_image(
+        "source",
+        image,
+        public_values.sourceWidth,
+        public_values.sourceHeight,
+        public_values.sourceHash.0,
+    )
+}
+
+fn check_image(
+    name: &str,
+    image: &GrayImage,
+    width: u32,
+    height: u32,
+    hash: [u8; 32],
+) -> Result<()> {
+    ensure!(
+        (image.width(), image.height()) == (width, height),
+        "the {} image is {}x{}, but the proof attests to a {}x{} image",
+        name,
+        image.width(),
+        image.height(),
+        width,
+        height
+    );
+    ensure!(
+        image_hash(image.as_raw()) == hash,
+        "the pixels of the {} image don't match the hash the proof attests to",
+        name
+    );
+    Ok(())
+}
+
+/// Prints the public values committed by the program.
+pub fn print_public_values(public_values: &PublicValuesStruct) {
+    println!("Source hash: {}", public_values.sourceHash);
+    println!(
+        "Source dimensions: {}x{}",
+        public_values.sourceWidth, public_values.sourceHeight
+    );
+    println!("Output hash: {}", public_values.outputHash);
+    println!(
+        "Output dimensions: {}x{}",
+        public_values.outputWidth, public_values.outputHeight
+    );
+    println!("Transform id: {}", public_values.transformId);
+}
@@ -0,0 +1,222 @@
+//! A registry of the program versions whose proofs are trusted, pinning each to its verification
+//! key and the digest of its ELF.
+//!
+//! Any program can produce public values that look like those of this one, so a proof only
+//! attests to a resize if it was verified with the key of a reviewed version of the program. The
+//! registry is a JSON file kept under version control: `vkey --pin` adds the current program to it,
+//! and `verify` rejects proofs whose verification key it doesn't list. The keys themselves are
+//! pinned, so that the proofs of a version still verify once the ELF has moved on.
+
+use crate::bundle::elf_hash;
+use anyhow::{ensure, Context, Result};
+use base64::prelude::{Engine, BASE64_STANDARD};
+use fibonacci_lib::TRANSFORM_RESIZE;
+use serde::{Deserialize, Serialize};
+use sp1_sdk::{HashableKey, ProverClient, SP1ProofWithPublicValues, SP1VerifyingKey};
+use std::fs;
+use std::path::Path;
+
+/// The registry of this project.
+pub const DEFAULT_REGISTRY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../vkeys.json");
+
+/// The transforms the program attests to.
+pub const TRANSFORMS: &[u32] = &[TRANSFORM_RESIZE];
+
+/// The pinned program versions.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Registry {
+    pub programs: Vec<PinnedProgram>,
+}
+
+/// A pinned version of the program.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PinnedProgram {
+    /// The version of the program, as named when pinning it.
+    pub version: String,
+    /// The transform ids the program attests to.
+    pub transforms: Vec<u32>,
+    /// The hash of the verification key, as the EVM verifier expects it.
+    pub vkey_hash: String,
+    /// The SHA-256 digest of the ELF, hex encoded.
+    pub elf_hash: String,
+    /// The verification key, encoded with bincode and base64.
+    pub vkey: String,
+}
+
+impl PinnedProgram {
+    /// This program, with the verification key `vk`, as the version `version`.
+    pub fn new(version: &str, vk: &SP1VerifyingKey) -> Result<Self> {
+        Ok(PinnedProgram {
+            version: version.to_string(),
+            transforms: TRANSFORMS.to_vec(),
+            vkey_hash: vk.bytes32(),
+            elf_hash: format!("0x{}", hex::encode(elf_hash())),
+            vkey: BASE64_STANDARD.encode(bincode::serialize(vk)?),
+        })
+    }
+
+    /// The verification key, checked against its hash.
+    pub fn verifying_key(&self) -> Result<SP1VerifyingKey> {
+        let bytes = BASE64_STANDARD.decode(&self.vkey).with_context(|| {
+            format!("invalid verification key for the version {}", self.version)
+        })?;
+        let vk: SP1VerifyingKey = bincode::deserialize(&bytes).with_context(|| {
+            format!("invalid verification key for the version {}", self.version)
+        })?;
+        ensure!(
+            vk.bytes32() == self.vkey_hash,
+            "the verification key of the version {} doesn't hash to {}",
+            self.version,
+            self.vkey_hash
+        );
+        Ok(vk)
+    }
+}
+
+impl Registry {
+    /// Reads a registry.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path).with_context(|| {
+            format!(
+                "cannot read the registry {}, pin the program with `vkey --pin`",
+                path.display()
+            )
+        })?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("{} is not a registry", path.display()))
+    }
+
+    /// Reads a registry, or returns an empty one if the file doesn't exist.
+    pub fn load_or_default(path: &Path) -> Result<Self> {
+        if path.exists() {
+            Self::load(path)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    /// Writes the registry.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut contents = serde_json::to_string_pretty(self)?;
+        contents.push('\n');
+        fs::write(path, contents).with_context(|| format!("cannot write {}", path.display()))
+    }
+
+    /// The pinned program with the verification key `vkey_hash`.
+    pub fn find(&self, vkey_hash: &str) -> Option<&PinnedProgram> {
+        self.programs
+            .iter()
+            .find(|program| program.vkey_hash == vkey_hash)
+    }
+
+    /// Checks that the verification key `vkey_hash` is pinned, returning its program.
+    pub fn check(&self, vkey_hash: &str) -> Result<&PinnedProgram> {
+        self.find(vkey_hash).with_context(|| {
+            format!(
+                "the verification key {} is not pinned in the registry",
+                vkey_hash
+            )
+        })
+    }
+
+    /// Verifies a proof with the key of the pinned program `vkey_hash`, returning the program.
+    pub fn verify(
+        &self,
+        client: &ProverClient,
+        proof: &SP1ProofWithPublicValues,
+        vkey_hash: &str,
+    ) -> Result<&PinnedProgram> {
+        let program = self.check(vkey_hash)?;
+        client
+            .verify(proof, &program.verifying_key()?)
+            .context("failed to verify proof")?;
+        Ok(program)
+    }
+
+    /// Verifies a proof of an unknown program with the keys of the pinned programs, latest first,
+    /// returning the program it verifies with. The mock prover accepts any key, so it returns the
+    /// latest program whose key is valid.
+    pub fn verify_any(
+        &self,
+        client: &ProverClient,
+        proof: &SP1ProofWithPublicValues,
+    ) -> Result<&PinnedProgram> {
+        self.programs
+            .iter()
+            .rev()
+            .find(|program| {
+                program
+                    .verifying_key()
+                    .is_ok_and(|vk| client.verify(proof, &vk).is_ok())
+            })
+            .context("the proof doesn't verify with the key of any pinned program")
+    }
+
+    /// Pins a program. Pinning it again is a no-op, but a version can't be pinned to another
+    /// program, nor a program under another version.
+    pub fn pin(&mut self, program: PinnedProgram) -> Result<()> {
+        for pinned in &self.programs {
+            if pinned == &program {
+                return Ok(());
+            }
+            ensure!(
+                pinned.version != program.version,
+                "the version {} is pinned to another program, bump the version",
+                program.version
+            );
+            ensure!(
+                pinned.vkey_hash != program.vkey_hash,
+                "the program is already pinned as the version {}",
+                pinned.version
+            );
+        }
+        self.programs.push(program);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn program(version: &str, vkey_hash: &str) -> PinnedProgram {
+        PinnedProgram {
+            version: version.to_string(),
+            transforms: TRANSFORMS.to_vec(),
+            vkey_hash: vkey_hash.to_string(),
+            elf_hash: "0x01".to_string(),
+            vkey: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_pin_and_check() {
+        let mut registry = Registry::default();
+        registry.pin(program("1.0.0", "0xaa")).unwrap();
+        registry.pin(program("1.0.0", "0xaa")).unwrap();
+        registry.pin(program("1.1.0", "0xbb")).unwrap();
+        assert_eq!(registry.programs.len(), 2);
+        assert_eq!(registry.check("0xbb").unwrap().version, "1.1.0");
+        assert!(registry.check("0xcc").is_err());
+
+        // A version or a key can't be pinned twice
+        assert!(registry.pin(program("1.0.0", "0xcc")).is_err());
+        assert!(registry.pin(program("1.2.0", "0xaa")).is_err());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vkeys.json");
+        assert_eq!(
+            Registry::load_or_default(&path).unwrap(),
+            Registry::default()
+        );
+        assert!(Registry::load(&path).is_err());
+
+        let mut registry = Registry::default();
+        registry.pin(program("1.0.0", "0xaa")).unwrap();
+        registry.save(&path).unwrap();
+        assert_eq!(Registry::load(&path).unwrap(), registry);
+    }
+}
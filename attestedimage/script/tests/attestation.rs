@@ -0,0 +1,470 @@
+//! End-to-end tests of the attestation pipeline with the mock prover: the program is executed on
+//! small images, and its public values are checked against a native run of `resize_image`.
+
+use fibonacci_lib::{image_hash, resize_image, PublicValuesStruct, TRANSFORM_RESIZE};
+use fibonacci_script::bundle::{elf_hash, Bundle, BUNDLE_EXTENSION};
+use fibonacci_script::c2pa::{Manifest, CLAIM_GENERATOR, SP1_ASSERTION_LABEL};
+use fibonacci_script::embed;
+use fibonacci_script::registry::{PinnedProgram, Registry, DEFAULT_REGISTRY};
+use fibonacci_script::{
+    check_output_image, check_source_image, decode_public_values, load_image, load_proof,
+    program_input, save_image, save_proof, SizeArgs, RESIZE_ELF,
+};
+use image::codecs::jpeg::JpegEncoder;
+use image::GrayImage;
+use sha2::{Digest, Sha256};
+use sp1_sdk::{
+    HashableKey, ProverClient, SP1ProofWithPublicValues, SP1ProvingKey, SP1VerifyingKey,
+};
+use std::path::Path;
+use std::process::Command;
+use std::sync::OnceLock;
+
+/// Setting up a prover client is slow, so the tests share one.
+fn client() -> &'static ProverClient {
+    static CLIENT: OnceLock<ProverClient> = OnceLock::new();
+    CLIENT.get_or_init(ProverClient::mock)
+}
+
+/// Setting up the keys of the program is slow too.
+fn keys() -> &'static (SP1ProvingKey, SP1VerifyingKey) {
+    static KEYS: OnceLock<(SP1ProvingKey, SP1VerifyingKey)> = OnceLock::new();
+    KEYS.get_or_init(|| client().setup(RESIZE_ELF))
+}
+
+/// The ELF of another SP1 program, the Fibonacci test program of `sp1-prover`.
+const OTHER_ELF: &[u8] = include_bytes!("fixtures/other-program-elf");
+
+/// Another program pinned as the version `version`, with its own valid verification key.
+fn other_program(version: &str) -> PinnedProgram {
+    static KEY: OnceLock<SP1VerifyingKey> = OnceLock::new();
+    let vk = KEY.get_or_init(|| client().setup(OTHER_ELF).1);
+    PinnedProgram {
+        elf_hash: format!("0x{}", hex::encode(Sha256::digest(OTHER_ELF))),
+        ..PinnedProgram::new(version, vk).unwrap()
+    }
+}
+
+/// An image whose pixels vary in both directions, so that a transposed or shifted resize shows.
+fn gradient(width: u32, height: u32) -> GrayImage {
+    GrayImage::from_fn(width, height, |x, y| {
+        image::Luma([((x * 255 / width.max(2) + y * 37) % 256) as u8])
+    })
+}
+
+fn native_resize(image: &GrayImage, dst_w: u32, dst_h: u32) -> GrayImage {
+    let mut output = vec![0u8; (dst_w * dst_h) as usize];
+    resize_image(
+        image.as_raw(),
+        &mut output,
+        image.width() as i32,
+        image.height() as i32,
+        dst_w as i32,
+        dst_h as i32,
+    );
+    GrayImage::from_raw(dst_w, dst_h, output).unwrap()
+}
+
+fn execute(image: &GrayImage, dst_w: u32, dst_h: u32) -> PublicValuesStruct {
+    let (output, _) = client()
+        .execute(RESIZE_ELF, program_input(image, dst_w, dst_h))
+        .run()
+        .expect("failed to execute the program");
+    decode_public_values(output.as_slice()).unwrap()
+}
+
+/// A mock proof of the resize of `image` by the program.
+fn prove(image: &GrayImage, dst_w: u32, dst_h: u32) -> SP1ProofWithPublicValues {
+    let (pk, _) = keys();
+    client()
+        .prove(pk, program_input(image, dst_w, dst_h))
+        .run()
+        .expect("failed to prove the resize")
+}
+
+fn assert_attests(public_values: &PublicValuesStruct, image: &GrayImage, dst_w: u32, dst_h: u32) {
+    let resized = native_resize(image, dst_w, dst_h);
+    assert_eq!(public_values.sourceHash.0, image_hash(image.as_raw()));
+    assert_eq!(public_values.outputHash.0, image_hash(resized.as_raw()));
+    assert_eq!(
+        (public_values.sourceWidth, public_values.sourceHeight),
+        image.dimensions()
+    );
+    assert_eq!(
+        (public_values.outputWidth, public_values.outputHeight),
+        (dst_w, dst_h)
+    );
+    assert_eq!(public_values.transformId, TRANSFORM_RESIZE);
+    check_source_image(public_values, image).unwrap();
+    check_output_image(public_values, &resized).unwrap();
+}
+
+#[test]
+fn test_execute_downscale() {
+    let image = gradient(16, 16);
+    assert_attests(&execute(&image, 8, 4), &image, 8, 4);
+}
+
+#[test]
+fn test_execute_horizontal_upscale() {
+    let image = gradient(5, 13);
+    assert_attests(&execute(&image, 12, 3), &image, 12, 3);
+}
+
+#[test]
+fn test_execute_filter_before_source() {
+    // The first row of a 2x vertical downscale or of an upscale starts above the image
+    for (src_w, src_h, dst_w, dst_h) in [(10, 6, 5, 3), (5, 3, 12, 7)] {
+        let image = gradient(src_w, src_h);
+        let result = client()
+            .execute(RESIZE_ELF, program_input(&image, dst_w, dst_h))
+            .run();
+        assert!(result.is_err());
+
+        let size = SizeArgs {
+            width: Some(dst_w),
+            height: Some(dst_h),
+            scale: None,
+        };
+        assert!(size.output_dimensions(src_w, src_h).is_err());
+    }
+}
+
+#[test]
+fn test_execute_sample_image() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../../resizing_video/sand_480270_Y_channel.txt");
+    let image = load_image(&path).unwrap();
+    assert_eq!(image.dimensions(), (480, 270));
+    assert_attests(&execute(&image, 120, 68), &image, 120, 68);
+}
+
+#[test]
+fn test_execute_invalid_dimensions() {
+    let image = gradient(4, 4);
+    let result = client()
+        .execute(RESIZE_ELF, program_input(&image, 0, 2))
+        .run();
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_prove_and_verify() {
+    let image = gradient(9, 13);
+    let (_, vk) = keys();
+    let proof = prove(&image, 4, 3);
+    client().verify(&proof, vk).unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("proof.bin");
+    save_proof(&path, &proof).unwrap();
+    let loaded = load_proof(&path).unwrap();
+    client().verify(&loaded, vk).unwrap();
+
+    let public_values = decode_public_values(loaded.public_values.as_slice()).unwrap();
+    assert_attests(&public_values, &image, 4, 3);
+
+    // A different image of the same size doesn't match
+    let mut tampered = native_resize(&image, 4, 3);
+    tampered.get_pixel_mut(0, 0).0[0] ^= 1;
+    assert!(check_output_image(&public_values, &tampered).is_err());
+}
+
+#[test]
+fn test_text_image_roundtrip() {
+    let image = gradient(7, 3);
+    let dir = tempfile::tempdir().unwrap();
+    for name in ["image.txt", "image.png"] {
+        let path = dir.path().join(name);
+        save_image(&path, &image).unwrap();
+        assert_eq!(load_image(&path).unwrap(), image);
+    }
+}
+
+#[test]
+fn test_bundle_roundtrip() {
+    let image = gradient(10, 13);
+    let resized = native_resize(&image, 5, 3);
+    let proof = prove(&image, 5, 3);
+    let (_, vk) = keys();
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = Bundle::new(&proof, vk, Some(&resized))
+        .unwrap()
+        .write(dir.path())
+        .unwrap();
+    let (bundle, id) = Bundle::read(&path).unwrap();
+    assert_eq!(
+        path.file_name().unwrap().to_str().unwrap(),
+        format!("{}.{}", id, BUNDLE_EXTENSION)
+    );
+    assert_eq!(
+        bundle.proof().unwrap().public_values.as_slice(),
+        proof.public_values.as_slice()
+    );
+    assert_eq!(bundle.output_image().unwrap(), Some(resized));
+
+    // Without an image
+    let path = Bundle::new(&proof, vk, None)
+        .unwrap()
+        .write(dir.path())
+        .unwrap();
+    assert_eq!(Bundle::read(&path).unwrap().0.output_image().unwrap(), None);
+}
+
+#[test]
+fn test_bundle_mismatch() {
+    let image = gradient(10, 13);
+    let proof = prove(&image, 5, 3);
+    let (_, vk) = keys();
+
+    // The image must be the one the proof attests to
+    let other = native_resize(&gradient(10, 14), 5, 3);
+    assert!(Bundle::new(&proof, vk, Some(&other)).is_err());
+
+    // Swapping the image of a bundle is detected when decoding it
+    let mut bundle = Bundle::new(&proof, vk, None).unwrap();
+    bundle.output_image = Bundle::new(&prove(&gradient(10, 14), 5, 3), vk, Some(&other))
+        .unwrap()
+        .output_image;
+    assert!(Bundle::from_bytes(&bundle.to_bytes().unwrap()).is_err());
+
+    // A bundle whose contents don't match its name is rejected
+    let dir = tempfile::tempdir().unwrap();
+    let path = Bundle::new(&proof, vk, None)
+        .unwrap()
+        .write(dir.path())
+        .unwrap();
+    let mut bytes = std::fs::read(&path).unwrap();
+    *bytes.last_mut().unwrap() ^= 1;
+    std::fs::write(&path, bytes).unwrap();
+    assert!(Bundle::read(&path).is_err());
+}
+
+#[test]
+fn test_embedded_bundle() {
+    let image = gradient(10, 13);
+    let resized = native_resize(&image, 5, 3);
+    let proof = prove(&image, 5, 3);
+    let (_, vk) = keys();
+    let bundle = Bundle::new(&proof, vk, None).unwrap().to_bytes().unwrap();
+
+    // The pixels of the image with the bundle still match the public values
+    let mut png = Vec::new();
+    resized
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .unwrap();
+    let attested = embed::embed(&png, &bundle).unwrap();
+    let extracted = Bundle::from_bytes(&embed::extract(&attested).unwrap().unwrap()).unwrap();
+    let public_values =
+        decode_public_values(extracted.proof().unwrap().public_values.as_slice()).unwrap();
+    let pixels = image::load_from_memory(&attested).unwrap().into_luma8();
+    check_output_image(&public_values, &pixels).unwrap();
+}
+
+#[test]
+fn test_embedded_bundle_jpeg() {
+    // A JPEG image attests only if it decodes to the exact resized pixels: at quality 100 a flat
+    // image does, as all its blocks are a DC coefficient that survives quantization
+    let image = GrayImage::from_pixel(10, 13, image::Luma([128]));
+    let resized = native_resize(&image, 5, 3);
+    let proof = prove(&image, 5, 3);
+    let (_, vk) = keys();
+    let bundle = Bundle::new(&proof, vk, None).unwrap().to_bytes().unwrap();
+    let jpeg = |image: &GrayImage| {
+        let mut jpeg = Vec::new();
+        image
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut jpeg, 100))
+            .unwrap();
+        jpeg
+    };
+
+    let attested = embed::embed(&jpeg(&resized), &bundle).unwrap();
+    let extracted = Bundle::from_bytes(&embed::extract(&attested).unwrap().unwrap()).unwrap();
+    let proof = extracted.proof().unwrap();
+    client().verify(&proof, vk).unwrap();
+    let public_values = decode_public_values(proof.public_values.as_slice()).unwrap();
+    let pixels = image::load_from_memory(&attested).unwrap().into_luma8();
+    check_output_image(&public_values, &pixels).unwrap();
+
+    // Lossy encoding of the pixels of a gradient doesn't give back the attested image
+    let image = gradient(10, 13);
+    let proof = prove(&image, 5, 3);
+    let public_values = decode_public_values(proof.public_values.as_slice()).unwrap();
+    let reencoded = jpeg(&native_resize(&image, 5, 3));
+    let pixels = image::load_from_memory(&reencoded).unwrap().into_luma8();
+    assert!(check_output_image(&public_values, &pixels).is_err());
+}
+
+#[test]
+fn test_committed_registry() {
+    // Every pinned version carries the verification key it is pinned to. The committed ELF isn't
+    // pinned until it is the reproducible Docker build `check-elf --docker` compares with.
+    let (_, vk) = keys();
+    let registry = Registry::load(Path::new(DEFAULT_REGISTRY)).unwrap();
+    for program in &registry.programs {
+        assert_eq!(
+            program.verifying_key().unwrap().bytes32(),
+            program.vkey_hash
+        );
+    }
+    if let Some(program) = registry.find(&vk.bytes32()) {
+        assert_eq!(program.elf_hash, format!("0x{}", hex::encode(elf_hash())));
+    }
+}
+
+#[test]
+fn test_verify_pinned_program() {
+    let image = gradient(10, 13);
+    let proof = prove(&image, 5, 3);
+    let (_, vk) = keys();
+    let dir = tempfile::tempdir().unwrap();
+    let proof_path = dir.path().join("proof.bin");
+    let image_path = dir.path().join("proof.png");
+    let attested_path = dir.path().join("attested.png");
+    save_proof(&proof_path, &proof).unwrap();
+    save_image(&image_path, &native_resize(&image, 5, 3)).unwrap();
+    let bundle = Bundle::new(&proof, vk, None).unwrap().to_bytes().unwrap();
+    let png = std::fs::read(&image_path).unwrap();
+    std::fs::write(&attested_path, embed::embed(&png, &bundle).unwrap()).unwrap();
+
+    let verify = |registry: &Registry, proof: Option<&Path>, image: &Path| {
+        let registry_path = dir.path().join("vkeys.json");
+        registry.save(&registry_path).unwrap();
+        let mut command = Command::new(env!("CARGO_BIN_EXE_verify"));
+        command.arg("--mock").arg("--image").arg(image);
+        if let Some(proof) = proof {
+            command.arg("--proof").arg(proof);
+        }
+        command
+            .arg("--registry")
+            .arg(&registry_path)
+            .output()
+            .unwrap()
+    };
+
+    // A bare proof doesn't name its key and the mock prover accepts any key, so it is only
+    // checked against a registry pinning this program
+    let mut registry = Registry::default();
+    registry
+        .pin(PinnedProgram::new("1.0.0", vk).unwrap())
+        .unwrap();
+    let output = verify(&registry, Some(&proof_path), &image_path);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Program version: 1.0.0"));
+
+    // A bundle of an older pinned version verifies with the key of that version
+    registry.pin(other_program("1.1.0")).unwrap();
+    let output = verify(&registry, None, &attested_path);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Program version: 1.0.0"));
+
+    // A registry pinning only another program, whose key is valid, doesn't trust the proof
+    let mut registry = Registry::default();
+    let other = other_program("1.0.0");
+    assert_ne!(other.vkey_hash, vk.bytes32());
+    other.verifying_key().unwrap();
+    registry.pin(other).unwrap();
+    let not_pinned = format!(
+        "the verification key {} is not pinned in the registry",
+        vk.bytes32()
+    );
+    let err = registry
+        .verify(client(), &proof, &vk.bytes32())
+        .unwrap_err();
+    assert_eq!(err.to_string(), not_pinned);
+    let output = verify(&registry, None, &attested_path);
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(&format!("Untrusted program: {}", not_pinned)),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn test_manifest() {
+    let image = gradient(10, 13);
+    let proof = prove(&image, 5, 3);
+    let (_, vk) = keys();
+    let bundle = Bundle::new(&proof, vk, None).unwrap();
+    let mut registry = Registry::default();
+    registry
+        .pin(PinnedProgram::new("1.0.0", vk).unwrap())
+        .unwrap();
+
+    let manifest = Manifest::new(
+        &bundle,
+        client(),
+        &registry,
+        "small.png",
+        "image/png",
+        "gradient.png",
+    )
+    .unwrap();
+    let json = serde_json::to_value(&manifest).unwrap();
+    assert_eq!(json["claim_generator"], CLAIM_GENERATOR);
+    assert!(CLAIM_GENERATOR.starts_with("attestedimage/"));
+    assert_eq!(json["ingredients"][0]["relationship"], "parentOf");
+    assert_eq!(json["ingredients"][0]["hash"]["width"], 10);
+    assert_eq!(json["assertions"][0]["label"], "c2pa.actions");
+    let action = &json["assertions"][0]["data"]["actions"][0];
+    assert_eq!(action["action"], "c2pa.resized");
+    assert_eq!(action["parameters"]["width"], 5);
+    assert_eq!(json["assertions"][1]["label"], SP1_ASSERTION_LABEL);
+    assert_eq!(json["assertions"][1]["data"]["program_version"], "1.0.0");
+
+    let decoded: Manifest = serde_json::from_value(json).unwrap();
+    assert_eq!(decoded, manifest);
+    assert_eq!(
+        decoded.proof().unwrap().public_values.as_slice(),
+        proof.public_values.as_slice()
+    );
+
+    // No manifest for the proof of a program that isn't pinned
+    let mut registry = Registry::default();
+    registry.pin(other_program("1.0.0")).unwrap();
+    let result = Manifest::new(
+        &bundle,
+        client(),
+        &registry,
+        "small.png",
+        "image/png",
+        "gradient.png",
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_evm_fixture() {
+    let image = gradient(10, 13);
+    let resized = native_resize(&image, 5, 3);
+    let (_, vk) = keys();
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("image.png");
+    save_image(&input, &image).unwrap();
+
+    for system in ["plonk", "groth16"] {
+        let fixture_path = dir.path().join(format!("{}-fixture.json", system));
+        let output = Command::new(env!("CARGO_BIN_EXE_evm"))
+            .arg("--mock")
+            .arg("--input")
+            .arg(&input)
+            .args(["--width", "5", "--height", "3", "--system", system])
+            .arg("--output")
+            .arg(&fixture_path)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+
+        let fixture: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&fixture_path).unwrap()).unwrap();
+        assert_eq!(fixture["system"], system);
+        assert_eq!(fixture["vkey"], vk.bytes32());
+        assert_eq!(
+            fixture["outputHash"],
+            format!("0x{}", hex::encode(image_hash(resized.as_raw())))
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use anyhow::{ensure, Result};
+use plonky2::field::extension::Extendable;
+use plonky2::hash::hash_types::RichField;
+use plonky2::iop::target::{BoolTarget, Target};
+use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use range_check::assert_permutation;
+use range_check::commitment::register_commitment;
+
+/// The private targets of an adjacency circuit.
+pub struct AdjacencyTargets {
+    /// The unsorted vector whose elements are range checked.
+    pub values: Vec<Target>,
+    /// The sorted concatenation of `values` and the range table.
+    pub sorted: Vec<Target>,
+    /// The exclusive upper bound of the range table.
+    pub range: u64,
+}
+
+/// Builds a circuit proving that every element of a private vector of length `len` lies in
+/// [0, range).
+///
+/// Let w = [0, 1, ..., range - 1] be the range table and z the sorted concatenation of the
+/// values v and w. We prove:
+///   1. z is a permutation of v || w (grand-product argument)
+///   2. z starts at 0 and ends at range - 1
+///   3. adjacent elements of z differ by 0 or 1
+///
+/// so z walks from 0 to range - 1 without skipping any value, and every v_i is one of its steps.
+///
+/// The public inputs are the Poseidon commitment to v, see `range_check::commitment`.
+pub fn build_adjacency_circuit<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    len: usize,
+    range: u64,
+) -> AdjacencyTargets {
+    assert!(range > 0, "the range table must not be empty");
+
+    let values = builder.add_virtual_targets(len);
+    let table: Vec<Target> = (0..range)
+        .map(|x| builder.constant(F::from_canonical_u64(x)))
+        .collect();
+    let sorted = builder.add_virtual_targets(len + range as usize);
+
+    // The sorted vector must be a permutation of the values and the range table
+    let unsorted = [values.as_slice(), table.as_slice()].concat();
+    assert_permutation(builder, &unsorted, &sorted);
+
+    // Pin both ends of the sorted vector to the ends of the range
+    builder.connect(sorted[0], table[0]);
+    builder.connect(sorted[sorted.len() - 1], table[table.len() - 1]);
+
+    // Check adjacent elements differ by 0 or 1, i.e. diff * (diff - 1) = 0
+    for pair in sorted.windows(2) {
+        let diff = builder.sub(pair[1], pair[0]);
+        builder.assert_bool(BoolTarget::new_unsafe(diff));
+    }
+
+    register_commitment(builder, &values);
+
+    AdjacencyTargets {
+        values,
+        sorted,
+        range,
+    }
+}
+
+/// Assigns the values to an adjacency circuit, sorting them together with the range table.
+pub fn set_adjacency_witness<F: RichField>(
+    pw: &mut PartialWitness<F>,
+    targets: &AdjacencyTargets,
+    values: &[u64],
+) -> Result<()> {
+    ensure!(
+        values.len() == targets.values.len(),
+        "circuit expects a vector of length {}, got {}",
+        targets.values.len(),
+        values.len()
+    );
+
+    let mut sorted: Vec<u64> = values.iter().copied().chain(0..targets.range).collect();
+    sorted.sort();
+
+    for (&target, &x) in targets.values.iter().zip(values) {
+        pw.set_target(target, F::from_canonical_u64(x));
+    }
+    for (&target, &x) in targets.sorted.iter().zip(&sorted) {
+        pw.set_target(target, F::from_canonical_u64(x));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use range_check::commitment::commitment_u64;
+    use plonky2::plonk::circuit_data::CircuitConfig;
+    use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    fn prove_and_verify(values: &[u64], range: u64) -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let targets = build_adjacency_circuit(&mut builder, values.len(), range);
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        set_adjacency_witness(&mut pw, &targets, values)?;
+        let proof = data.prove(pw)?;
+        ensure!(proof.public_inputs == commitment_u64::<F>(values).elements);
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_values_in_range() -> Result<()> {
+        prove_and_verify(&[4, 1, 2, 15, 0, 4], 16)
+    }
+
+    #[test]
+    #[should_panic(expected = "was set twice with different values")]
+    fn test_value_out_of_range() {
+        prove_and_verify(&[4, 1, 2, 16], 16).unwrap();
+    }
+}
@@ -1,15 +1,32 @@
-mod util;
-
-use anyhow::Result;
-use plonky2::field::types::{Field, PrimeField64};
+use adjacency_check::{build_adjacency_circuit, set_adjacency_witness};
+use anyhow::{ensure, Result};
+use clap::Parser;
 use plonky2::iop::witness::PartialWitness;
 use plonky2::plonk::circuit_builder::CircuitBuilder;
 use plonky2::plonk::circuit_data::CircuitConfig;
 use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+use range_check::commitment::commitment_u64;
+use range_check_input::{read_vector, Format};
+use std::path::PathBuf;
 use std::time::Instant;
-use plonky2::iop::target::Target;
+
+// The values are pixels, so they must lie in [0, 256)
+const RANGE: u64 = 256;
+
+/// Proves that every element of a vector lies in [0, 256) with the adjacency argument.
+#[derive(Parser, Debug)]
+struct Args {
+    /// The vector to range check.
+    #[arg(long, default_value = "resources/vec1.txt")]
+    input: PathBuf,
+    /// Format of the input files (detected from the extension and contents by default).
+    #[arg(long, value_enum)]
+    format: Option<Format>,
+}
 
 fn main() -> Result<()> {
+    let args = Args::parse();
+
     //
     // STEP1: boilerplate code of circuit setup and data preparation
     const D: usize = 2;
@@ -21,43 +38,16 @@ fn main() -> Result<()> {
     let config: CircuitConfig = CircuitConfig::standard_recursion_config();
     let mut builder = CircuitBuilder::<F, D>::new(config);
 
-    let vec: Vec<u64> = util::read_vector_from_file("resources/vec1.txt")?;
-    let vec_field: Vec<F> = vec.iter()
-                                                 .map(|&x| F::from_canonical_u64(x))
-                                                 .collect();
+    let vec: Vec<u64> = read_vector(&args.input, args.format)?;
 
     //
     // STEP2: build the circuit by adding constraints of checking adjacency
-    // The public statement is "I have a vector, that aftering sorted, the adjacent element "
-    // The secret witness is the vecs, which generate the proof and are hidden from public
+    // The public statement is "I have a vector whose elements all lie in [0, RANGE)"
+    // The secret witness is the unsorted vec together with its sorted concatenation with the
+    // range table, both assigned through the PartialWitness. The circuit proves the sorted vector
+    // is a permutation of the unsorted one and that its adjacent elements differ by 0 or 1.
     //
-                                                
-    // Convert field elements to a type that supports sorting
-    let mut vec_sorted: Vec<u64> = vec_field.iter().map(|&x| x.to_canonical_u64()).collect();
-    vec_sorted.sort();
-
-    // Convert sorted elements back to field elements
-    let sorted_vec_field: Vec<F> = vec_sorted.iter().map(|&x| F::from_canonical_u64(x)).collect();
-    // Convert sorted elements to Targets
-    let sorted_vec_targets: Vec<Target> = sorted_vec_field.iter().map(|&x| builder.constant(x)).collect();
-
-    // Check adjacent elements differ by 0 or 1
-    // NOTE: didn't find a succinct way to check boolean, so the BoolTarget 
-    // is converted to a numeric Target
-    for i in 0..sorted_vec_field.len() - 1 {
-        let diff = builder.sub(sorted_vec_targets[i + 1], sorted_vec_targets[i]);
-        let zero = builder.zero();
-        let one = builder.one();
-        let diff_zero = builder.is_equal(diff, zero);
-        let diff_one = builder.is_equal(diff, one);
-
-        // Convert boolean targets to numerical targets
-        let diff_zero_num = builder.select(diff_zero, one, zero);
-        let diff_one_num = builder.select(diff_one, one, zero);
-
-        let diff_valid = builder.add(diff_zero_num, diff_one_num);
-        builder.connect(diff_valid, one);
-    }
+    let targets = build_adjacency_circuit(&mut builder, vec.len(), RANGE);
 
     // boilerplate code for benchmark, prove and verify
     let start_build = Instant::now();
@@ -65,13 +55,21 @@ fn main() -> Result<()> {
     let build_duration = start_build.elapsed();
     println!("Circuit built in: {:?}", build_duration);
 
+    let mut pw = PartialWitness::new();
+    set_adjacency_witness(&mut pw, &targets, &vec)?;
+
     let start_prove = Instant::now();
-    let pw = PartialWitness::new();
     let proof = data.prove(pw)?;
     let prove_duration = start_prove.elapsed();
     println!("Proof generated in: {:?}", prove_duration);
 
-    println!("Permutation proof generated successfully.");
+    println!("Adjacency proof generated successfully.");
+
+    // The proof commits to the private values, a verifier holding them recomputes the digest
+    ensure!(
+        proof.public_inputs == commitment_u64::<F>(&vec).elements,
+        "proof doesn't commit to the values"
+    );
 
     let start_verify = Instant::now();
     data.verify(proof)?;
@@ -0,0 +1,114 @@
+mod strategy;
+
+use anyhow::{ensure, Result};
+use clap::{Parser, ValueEnum};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use strategy::{Measurement, Strategy};
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Format {
+    Csv,
+    Json,
+}
+
+/// Runs every range-check strategy over generated inputs and reports gate count (or FRI domain
+/// size), proof size and timings.
+#[derive(Parser, Debug)]
+struct Args {
+    /// Strategies to benchmark (defaults to all of them).
+    #[arg(long, value_enum, value_delimiter = ',')]
+    strategies: Vec<Strategy>,
+    /// Smallest input size, as a power of two.
+    #[arg(long, default_value_t = 10)]
+    min_log_size: usize,
+    /// Largest input size, as a power of two.
+    #[arg(long, default_value_t = 20)]
+    max_log_size: usize,
+    /// Range widths in bits, i.e. values are checked to lie in [0, 2^range_bits).
+    #[arg(long, value_delimiter = ',', default_value = "8")]
+    range_bits: Vec<usize>,
+    /// Seed of the input generator.
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+    #[arg(long, value_enum, default_value_t = Format::Csv)]
+    format: Format,
+    /// Where to write the results (defaults to stdout).
+    #[arg(long)]
+    output: Option<PathBuf>,
+}
+
+fn main() -> Result<()> {
+    let mut args = Args::parse();
+    ensure!(
+        args.min_log_size <= args.max_log_size,
+        "--min-log-size must not exceed --max-log-size"
+    );
+    if args.strategies.is_empty() {
+        args.strategies = Strategy::value_variants().to_vec();
+    }
+    // Reject the inputs a strategy cannot handle before spending time on the others
+    for &range_bits in &args.range_bits {
+        for log_size in args.min_log_size..=args.max_log_size {
+            for &strategy in &args.strategies {
+                strategy.validate(log_size, range_bits)?;
+            }
+        }
+    }
+
+    let mut rng = StdRng::seed_from_u64(args.seed);
+    let mut measurements = Vec::new();
+    for &range_bits in &args.range_bits {
+        for log_size in args.min_log_size..=args.max_log_size {
+            for &strategy in &args.strategies {
+                eprintln!("{:?}: 2^{} values, {} bits", strategy, log_size, range_bits);
+                measurements.push(strategy.run(log_size, range_bits, &mut rng)?);
+            }
+        }
+    }
+
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout()),
+    };
+    match args.format {
+        Format::Csv => write_csv(&mut out, &measurements)?,
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut out, &measurements)?;
+            writeln!(out)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_csv(out: &mut impl Write, measurements: &[Measurement]) -> Result<()> {
+    writeln!(
+        out,
+        "strategy,log_size,range_bits,gates,domain_size,proof_bytes,build_ms,prove_ms,verify_ms"
+    )?;
+    for m in measurements {
+        let strategy = m.strategy.to_possible_value().expect("no skipped variants");
+        writeln!(
+            out,
+            "{},{},{},{},{},{},{},{:.3},{:.3}",
+            strategy.get_name(),
+            m.log_size,
+            m.range_bits,
+            csv_cell(m.gates),
+            csv_cell(m.domain_size),
+            m.proof_bytes,
+            csv_cell(m.build_ms.map(|ms| format!("{:.3}", ms))),
+            m.prove_ms,
+            m.verify_ms
+        )?;
+    }
+    Ok(())
+}
+
+/// An optional value, left empty when it doesn't apply to the strategy.
+fn csv_cell(value: Option<impl ToString>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
@@ -0,0 +1,267 @@
+use anyhow::{ensure, Result};
+use clap::ValueEnum;
+use plonky2::iop::witness::PartialWitness;
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::CircuitConfig;
+use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+use polynomial_permutation_check::security::FriPreset;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::Serialize;
+use std::time::Instant;
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+/// Largest range, in bits, of the strategies holding the whole range table: 2^16 table entries
+/// already dwarf the inputs of the smaller sizes.
+const MAX_TABLE_BITS: usize = 16;
+
+/// The range-check strategies implemented in the `range_check` workspace.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Strategy {
+    /// plonky2's built-in `range_check` gadget (native_range_check_benchmark).
+    Native,
+    /// Sorted vector with adjacent differences in {0, 1} (adjacency_check).
+    Adjacency,
+    /// Bit decomposition of (x - lower) and (upper - x) (head_tail_check).
+    HeadTail,
+    /// Grand-product permutation argument between a vector and its shuffle (permutation_check).
+    Permutation,
+    /// Plookup-style argument over FRI polynomial commitments (polynomial_permutation_check).
+    Polynomial,
+    /// LogUp lookup argument over the same FRI commitments (polynomial_permutation_check::logup).
+    #[value(name = "logup")]
+    #[serde(rename = "logup")]
+    LogUp,
+    /// Sum of squared differences of two frames below a threshold (diff_square_sum). Not a range
+    /// check itself, but built from one per pixel and compared with the Circom/Noir versions.
+    DiffSquareSum,
+}
+
+/// The cost of one strategy on one input.
+#[derive(Debug, Serialize)]
+pub struct Measurement {
+    pub strategy: Strategy,
+    pub log_size: usize,
+    pub range_bits: usize,
+    /// Number of rows of the built plonky2 circuit, padding and constant gates included, `None` for
+    /// the FRI-based strategies.
+    pub gates: Option<usize>,
+    /// Size of the FRI evaluation domain of `Strategy::Polynomial` and `Strategy::LogUp`, `None`
+    /// for the plonky2 circuits.
+    pub domain_size: Option<usize>,
+    pub proof_bytes: usize,
+    /// Time to build the plonky2 circuit, `None` for the FRI-based strategies which have none.
+    pub build_ms: Option<f64>,
+    pub prove_ms: f64,
+    pub verify_ms: f64,
+}
+
+impl Strategy {
+    /// Checks the strategy can handle 2^log_size values of `range_bits` bits.
+    pub fn validate(self, log_size: usize, range_bits: usize) -> Result<()> {
+        ensure!(
+            (1..32).contains(&range_bits),
+            "{:?}: the range must be between 1 and 31 bits, got {}",
+            self,
+            range_bits
+        );
+        match self {
+            Strategy::Adjacency | Strategy::Polynomial | Strategy::LogUp => ensure!(
+                range_bits <= MAX_TABLE_BITS,
+                "{:?}: the range table of {} bits exceeds {} bits",
+                self,
+                range_bits,
+                MAX_TABLE_BITS
+            ),
+            Strategy::DiffSquareSum => {
+                let sum_bits = diff_square_sum::sum_bits(1 << log_size, range_bits);
+                ensure!(
+                    sum_bits < 63,
+                    "{:?}: the sum of 2^{} squared differences of {} bits may wrap around the field",
+                    self,
+                    log_size,
+                    range_bits
+                )
+            }
+            Strategy::Native | Strategy::HeadTail | Strategy::Permutation => {}
+        }
+        Ok(())
+    }
+
+    /// Generates an input of 2^log_size values with `range_bits` wide range, then builds, proves and
+    /// verifies it.
+    pub fn run(self, log_size: usize, range_bits: usize, rng: &mut StdRng) -> Result<Measurement> {
+        self.validate(log_size, range_bits)?;
+        let size = 1 << log_size;
+        let range = 1u64 << range_bits;
+        let values: Vec<u64> = (0..size).map(|_| rng.gen_range(0..range)).collect();
+
+        let measurement = match self {
+            Strategy::Native => {
+                let values: Vec<usize> = values.iter().map(|&x| x as usize).collect();
+                measure_circuit(
+                    |builder| {
+                        native_range_check_benchmark::build_range_check_circuit(
+                            builder, size, range_bits,
+                        )
+                    },
+                    |pw, targets| {
+                        native_range_check_benchmark::set_range_check_witness(pw, targets, &values)
+                    },
+                )?
+            }
+            Strategy::Adjacency => measure_circuit(
+                |builder| adjacency_check::build_adjacency_circuit(builder, size, range),
+                |pw, targets| adjacency_check::set_adjacency_witness(pw, targets, &values),
+            )?,
+            Strategy::HeadTail => {
+                // Shift the values so the range is signed: [-2^(range_bits-1), 2^(range_bits-1))
+                let half = (range / 2) as i64;
+                let values: Vec<i64> = values.iter().map(|&x| x as i64 - half).collect();
+                measure_circuit(
+                    |builder| head_tail_check::build_head_tail_circuit(builder, size, range_bits),
+                    |pw, targets| {
+                        head_tail_check::set_head_tail_witness(
+                            pw,
+                            targets,
+                            &values,
+                            -half,
+                            half - 1,
+                        )
+                    },
+                )?
+            }
+            Strategy::Permutation => {
+                let mut shuffled = values.clone();
+                shuffled.shuffle(rng);
+                measure_circuit(
+                    |builder| range_check::build_permutation_circuit(builder, size),
+                    |pw, targets| {
+                        range_check::set_permutation_witness(pw, targets, &values, &shuffled)
+                    },
+                )?
+            }
+            Strategy::Polynomial => {
+                use polynomial_permutation_check::plookup;
+                let fri_config = FriPreset::default().config();
+                measure_fri(
+                    || plookup::prove(&values, range_bits, &fri_config),
+                    |proof| plookup::verify(proof, range_bits, &fri_config),
+                    |proof| (1 << proof.degree_bits, proof.to_bytes().len()),
+                )?
+            }
+            Strategy::LogUp => {
+                use polynomial_permutation_check::logup;
+                let fri_config = FriPreset::default().config();
+                measure_fri(
+                    || logup::prove(&values, range_bits, &fri_config),
+                    |proof| logup::verify(proof, range_bits, &fri_config),
+                    |proof| (1 << proof.degree_bits, proof.to_bytes().len()),
+                )?
+            }
+            Strategy::DiffSquareSum => {
+                // A second frame of the same size, the threshold is just above their distance
+                let other: Vec<u64> = (0..size).map(|_| rng.gen_range(0..range)).collect();
+                let threshold = diff_square_sum::diff_square_sum(&values, &other) + 1;
+                measure_circuit(
+                    |builder| {
+                        diff_square_sum::build_diff_square_sum_circuit(builder, size, range_bits)
+                    },
+                    |pw, targets| {
+                        diff_square_sum::set_diff_square_sum_witness(
+                            pw, targets, &values, &other, threshold,
+                        )
+                    },
+                )?
+            }
+        };
+
+        Ok(Measurement {
+            strategy: self,
+            log_size,
+            range_bits,
+            gates: measurement.gates,
+            domain_size: measurement.domain_size,
+            proof_bytes: measurement.proof_bytes,
+            build_ms: measurement.build_ms,
+            prove_ms: measurement.prove_ms,
+            verify_ms: measurement.verify_ms,
+        })
+    }
+}
+
+struct Cost {
+    gates: Option<usize>,
+    domain_size: Option<usize>,
+    proof_bytes: usize,
+    build_ms: Option<f64>,
+    prove_ms: f64,
+    verify_ms: f64,
+}
+
+/// Builds, proves and verifies a plonky2 circuit, replacing the build/prove/verify boilerplate of
+/// the individual range-check binaries.
+fn measure_circuit<T>(
+    build: impl FnOnce(&mut CircuitBuilder<F, D>) -> T,
+    set_witness: impl FnOnce(&mut PartialWitness<F>, &T) -> Result<()>,
+) -> Result<Cost> {
+    let config = CircuitConfig::standard_recursion_config();
+    let mut builder = CircuitBuilder::<F, D>::new(config);
+
+    let start_build = Instant::now();
+    let targets = build(&mut builder);
+    let data = builder.build::<C>();
+    let build_duration = start_build.elapsed();
+
+    let mut pw = PartialWitness::new();
+    set_witness(&mut pw, &targets)?;
+
+    let start_prove = Instant::now();
+    let proof = data.prove(pw)?;
+    let prove_duration = start_prove.elapsed();
+    let proof_bytes = proof.to_bytes().len();
+
+    let start_verify = Instant::now();
+    data.verify(proof)?;
+    let verify_duration = start_verify.elapsed();
+
+    Ok(Cost {
+        gates: Some(data.common.degree()),
+        domain_size: None,
+        proof_bytes,
+        build_ms: Some(build_duration.as_secs_f64() * 1000.0),
+        prove_ms: prove_duration.as_secs_f64() * 1000.0,
+        verify_ms: verify_duration.as_secs_f64() * 1000.0,
+    })
+}
+
+/// Proves and verifies with one of the FRI-based arguments, which have no circuit to build. `size`
+/// returns the size of the evaluation domain and of the serialized proof.
+fn measure_fri<P>(
+    prove: impl FnOnce() -> Result<P>,
+    verify: impl FnOnce(&P) -> Result<()>,
+    size: impl FnOnce(&P) -> (usize, usize),
+) -> Result<Cost> {
+    let start_prove = Instant::now();
+    let proof = prove()?;
+    let prove_duration = start_prove.elapsed();
+
+    let start_verify = Instant::now();
+    verify(&proof)?;
+    let verify_duration = start_verify.elapsed();
+
+    let (domain_size, proof_bytes) = size(&proof);
+    Ok(Cost {
+        gates: None,
+        domain_size: Some(domain_size),
+        proof_bytes,
+        build_ms: None,
+        prove_ms: prove_duration.as_secs_f64() * 1000.0,
+        verify_ms: verify_duration.as_secs_f64() * 1000.0,
+    })
+}
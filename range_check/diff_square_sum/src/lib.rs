@@ -0,0 +1,160 @@
+use anyhow::{ensure, Result};
+use plonky2::field::extension::Extendable;
+use plonky2::hash::hash_types::RichField;
+use plonky2::iop::target::Target;
+use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::util::log2_ceil;
+use range_check::commitment::register_commitment;
+
+/// The targets of a diff-square-sum circuit.
+pub struct DiffSquareSumTargets {
+    /// The private pixels of the first frame.
+    pub frame1: Vec<Target>,
+    /// The private pixels of the second frame.
+    pub frame2: Vec<Target>,
+    /// The public exclusive upper bound of the sum.
+    pub threshold: Target,
+}
+
+/// Returns the number of bits of sum((a_i - b_i)^2) over `len` pixels of `pixel_bits` bits.
+pub fn sum_bits(len: usize, pixel_bits: usize) -> usize {
+    log2_ceil(len.max(1)) + 2 * pixel_bits
+}
+
+/// Builds a circuit proving that two private frames of `len` pixels are similar, i.e.
+/// sum((a_i - b_i)^2) < threshold where the threshold is a public input.
+///
+/// This is the Circom `SquareRootSum` template with the pixels range checked to `pixel_bits` bits
+/// and the comparison done in-circuit. Since every pixel is small, the sum has at most
+/// `sum_bits(len, pixel_bits)` bits and doesn't wrap around the field, so we prove
+/// sum < threshold by range checking threshold - 1 - sum to the same number of bits: if
+/// sum >= threshold the difference wraps around to a field element close to p.
+///
+/// The public inputs are the threshold followed by the Poseidon commitments to both frames, see
+/// `range_check::commitment`.
+pub fn build_diff_square_sum_circuit<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    len: usize,
+    pixel_bits: usize,
+) -> DiffSquareSumTargets {
+    let num_bits = sum_bits(len, pixel_bits);
+    assert!(
+        num_bits < 63,
+        "the sum of {} pixels may wrap around the field",
+        len
+    );
+
+    let threshold = builder.add_virtual_public_input();
+    let frame1 = builder.add_virtual_targets(len);
+    let frame2 = builder.add_virtual_targets(len);
+
+    let mut sum = builder.zero();
+    for (&a, &b) in frame1.iter().zip(&frame2) {
+        builder.range_check(a, pixel_bits);
+        builder.range_check(b, pixel_bits);
+        let diff = builder.sub(a, b);
+        // sum += diff * diff
+        sum = builder.mul_add(diff, diff, sum);
+    }
+
+    // threshold - 1 - sum >= 0
+    let one = builder.one();
+    let bound = builder.sub(threshold, one);
+    let slack = builder.sub(bound, sum);
+    builder.range_check(slack, num_bits);
+
+    register_commitment(builder, &frame1);
+    register_commitment(builder, &frame2);
+
+    DiffSquareSumTargets {
+        frame1,
+        frame2,
+        threshold,
+    }
+}
+
+/// Assigns the frames and the threshold to a diff-square-sum circuit.
+pub fn set_diff_square_sum_witness<F: RichField>(
+    pw: &mut PartialWitness<F>,
+    targets: &DiffSquareSumTargets,
+    frame1: &[u64],
+    frame2: &[u64],
+    threshold: u64,
+) -> Result<()> {
+    ensure!(
+        frame1.len() == targets.frame1.len() && frame2.len() == targets.frame2.len(),
+        "circuit expects two frames of {} pixels, got {} and {}",
+        targets.frame1.len(),
+        frame1.len(),
+        frame2.len()
+    );
+
+    pw.set_target(targets.threshold, F::from_canonical_u64(threshold));
+    for (&target, &x) in targets.frame1.iter().zip(frame1) {
+        pw.set_target(target, F::from_canonical_u64(x));
+    }
+    for (&target, &x) in targets.frame2.iter().zip(frame2) {
+        pw.set_target(target, F::from_canonical_u64(x));
+    }
+    Ok(())
+}
+
+/// Computes sum((a_i - b_i)^2) natively.
+pub fn diff_square_sum(frame1: &[u64], frame2: &[u64]) -> u64 {
+    frame1
+        .iter()
+        .zip(frame2)
+        .map(|(&a, &b)| a.abs_diff(b).pow(2))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use range_check::commitment::commitment_u64;
+    use plonky2::field::types::Field;
+    use plonky2::plonk::circuit_data::CircuitConfig;
+    use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    fn prove_and_verify(frame1: &[u64], frame2: &[u64], threshold: u64) -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let targets = build_diff_square_sum_circuit(&mut builder, frame1.len(), 8);
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        set_diff_square_sum_witness(&mut pw, &targets, frame1, frame2, threshold)?;
+        let proof = data.prove(pw)?;
+        let mut public_inputs = vec![F::from_canonical_u64(threshold)];
+        public_inputs.extend(commitment_u64::<F>(frame1).elements);
+        public_inputs.extend(commitment_u64::<F>(frame2).elements);
+        ensure!(proof.public_inputs == public_inputs);
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_sum_below_threshold() -> Result<()> {
+        let frame1 = [0, 255, 10, 128];
+        let frame2 = [255, 0, 12, 128];
+        // 255^2 + 255^2 + 2^2 = 130054
+        assert_eq!(diff_square_sum(&frame1, &frame2), 130054);
+        prove_and_verify(&frame1, &frame2, 130055)
+    }
+
+    #[test]
+    #[should_panic(expected = "Integer too large to fit in")]
+    fn test_sum_equal_to_threshold() {
+        prove_and_verify(&[0, 255, 10, 128], &[255, 0, 12, 128], 130054).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "was set twice with different values")]
+    fn test_pixel_out_of_range() {
+        prove_and_verify(&[256, 1], &[255, 1], 100).unwrap();
+    }
+}
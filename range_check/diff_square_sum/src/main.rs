@@ -0,0 +1,92 @@
+use anyhow::{ensure, Result};
+use clap::Parser;
+use diff_square_sum::{
+    build_diff_square_sum_circuit, diff_square_sum, set_diff_square_sum_witness,
+};
+use plonky2::iop::witness::PartialWitness;
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::CircuitConfig;
+use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+use range_check::commitment::commitment_u64;
+use range_check_input::{read_vector, Format};
+use std::path::PathBuf;
+use std::time::Instant;
+
+// The frames are 8-bit pixel planes
+const PIXEL_BITS: usize = 8;
+
+/// Proves that the sum of squared differences of two private frames is below a public threshold.
+#[derive(Parser, Debug)]
+struct Args {
+    /// The first frame.
+    #[arg(long, default_value = "resources/vec1.txt")]
+    frame1: PathBuf,
+    /// The second frame.
+    #[arg(long, default_value = "resources/vec2.txt")]
+    frame2: PathBuf,
+    /// The exclusive upper bound of sum((a_i - b_i)^2).
+    #[arg(long, default_value_t = 700)]
+    threshold: u64,
+    /// Format of the input files (detected from the extension and contents by default).
+    #[arg(long, value_enum)]
+    format: Option<Format>,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    let config = CircuitConfig::standard_recursion_config();
+    let mut builder = CircuitBuilder::<F, D>::new(config);
+
+    let frame1: Vec<u64> = read_vector(&args.frame1, args.format)?;
+    let frame2: Vec<u64> = read_vector(&args.frame2, args.format)?;
+    ensure!(
+        frame1.len() == frame2.len(),
+        "frames of different sizes cannot be compared"
+    );
+
+    // The frames are private targets, the threshold is the only public input
+    let targets = build_diff_square_sum_circuit(&mut builder, frame1.len(), PIXEL_BITS);
+
+    // Boilerplate code for benchmark, prove and verify
+    let start_build = Instant::now();
+    let data = builder.build::<C>();
+    let build_duration = start_build.elapsed();
+    println!("Circuit built in: {:?}", build_duration);
+
+    let mut pw = PartialWitness::new();
+    set_diff_square_sum_witness(&mut pw, &targets, &frame1, &frame2, args.threshold)?;
+
+    println!(
+        "Sum of squared differences: {} (threshold {})",
+        diff_square_sum(&frame1, &frame2),
+        args.threshold
+    );
+
+    let start_prove = Instant::now();
+    let proof = data.prove(pw)?;
+    let prove_duration = start_prove.elapsed();
+    println!("Proof generated in: {:?}", prove_duration);
+
+    println!("Diff-square-sum proof generated successfully.");
+
+    // The proof commits to the private frames, a verifier holding them recomputes the digests
+    let digests = [commitment_u64::<F>(&frame1), commitment_u64::<F>(&frame2)];
+    ensure!(
+        proof.public_inputs[1..] == digests.map(|digest| digest.elements).concat(),
+        "proof doesn't commit to the frames"
+    );
+
+    let start_verify = Instant::now();
+    data.verify(proof)?;
+    let verify_duration = start_verify.elapsed();
+    println!("Proof verified in: {:?}", verify_duration);
+
+    println!("Proof verified successfully.");
+
+    Ok(())
+}
@@ -0,0 +1,149 @@
+use anyhow::{ensure, Result};
+use plonky2::field::extension::Extendable;
+use plonky2::hash::hash_types::RichField;
+use plonky2::iop::target::Target;
+use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use range_check::commitment::register_commitment;
+use range_check_signed::{encode_i64, encode_i64_slice};
+
+/// The targets of a head-tail circuit.
+pub struct HeadTailTargets {
+    /// The private values that are range checked.
+    pub values: Vec<Target>,
+    /// The public lower bound (inclusive).
+    pub lower: Target,
+    /// The public upper bound (inclusive).
+    pub upper: Target,
+}
+
+/// Builds a circuit proving that every element of a private vector of length `len` lies in
+/// [lower, upper], where both bounds are public inputs.
+///
+/// For each value x we decompose (x - lower) and (upper - x) into `num_bits` bits, which proves
+/// both differences are "small" non-negative integers rather than wrapped-around field elements.
+/// The same decomposition of (upper - lower) bounds the width of the range, so any signed range
+/// with upper - lower < 2^num_bits (e.g. [-255, 255] with num_bits = 9) fits the circuit.
+///
+/// The public inputs are lower, upper and the Poseidon commitment to the encoded values, see
+/// `range_check::commitment`.
+pub fn build_head_tail_circuit<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    len: usize,
+    num_bits: usize,
+) -> HeadTailTargets {
+    let lower = builder.add_virtual_public_input();
+    let upper = builder.add_virtual_public_input();
+
+    let width = builder.sub(upper, lower);
+    builder.range_check(width, num_bits);
+
+    let values = builder.add_virtual_targets(len);
+    for &value in &values {
+        // head: x - lower >= 0
+        let head = builder.sub(value, lower);
+        builder.range_check(head, num_bits);
+        // tail: upper - x >= 0
+        let tail = builder.sub(upper, value);
+        builder.range_check(tail, num_bits);
+    }
+
+    register_commitment(builder, &values);
+
+    HeadTailTargets {
+        values,
+        lower,
+        upper,
+    }
+}
+
+/// The number of bits of (x - lower) and (upper - x) for the range [lower, upper], that of
+/// upper - lower, e.g. 9 for [-255, 255].
+///
+/// Fails if the range is empty, or too wide for the differences to stay below the field modulus.
+pub fn range_bits(lower: i64, upper: i64) -> Result<usize> {
+    ensure!(lower <= upper, "empty range [{}, {}]", lower, upper);
+    let width = upper.abs_diff(lower);
+    let bits = (u64::BITS - width.leading_zeros()).max(1) as usize;
+    ensure!(bits < 64, "range [{}, {}] is too wide", lower, upper);
+    Ok(bits)
+}
+
+/// Assigns the signed values and bounds to a head-tail circuit, see `range_check_signed` for
+/// their encoding.
+pub fn set_head_tail_witness<F: RichField>(
+    pw: &mut PartialWitness<F>,
+    targets: &HeadTailTargets,
+    values: &[i64],
+    lower: i64,
+    upper: i64,
+) -> Result<()> {
+    ensure!(
+        values.len() == targets.values.len(),
+        "circuit expects a vector of length {}, got {}",
+        targets.values.len(),
+        values.len()
+    );
+
+    pw.set_target(targets.lower, encode_i64(lower));
+    pw.set_target(targets.upper, encode_i64(upper));
+    for (&target, value) in targets.values.iter().zip(encode_i64_slice(values)) {
+        pw.set_target(target, value);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use range_check::commitment::commitment;
+    use plonky2::plonk::circuit_data::CircuitConfig;
+    use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    fn prove_and_verify(values: &[i64], lower: i64, upper: i64) -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let targets = build_head_tail_circuit(&mut builder, values.len(), 9);
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        set_head_tail_witness(&mut pw, &targets, values, lower, upper)?;
+        let proof = data.prove(pw)?;
+        let digest = commitment(&encode_i64_slice::<F>(values));
+        ensure!(proof.public_inputs[..2] == encode_i64_slice::<F>(&[lower, upper]));
+        ensure!(proof.public_inputs[2..] == digest.elements);
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_range_bits() -> Result<()> {
+        assert_eq!(range_bits(-10, 10)?, 5);
+        assert_eq!(range_bits(-255, 255)?, 9);
+        assert_eq!(range_bits(0, 256)?, 9);
+        assert_eq!(range_bits(3, 3)?, 1);
+        assert!(range_bits(1, 0).is_err());
+        assert!(range_bits(i64::MIN, i64::MAX).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_signed_values_in_range() -> Result<()> {
+        prove_and_verify(&[-255, -10, 0, 7, 255], -255, 255)
+    }
+
+    #[test]
+    #[should_panic(expected = "Integer too large to fit in given number of limbs")]
+    fn test_value_below_lower_bound() {
+        prove_and_verify(&[-11, 0, 7], -10, 10).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "Integer too large to fit in given number of limbs")]
+    fn test_value_above_upper_bound() {
+        prove_and_verify(&[-10, 0, 11], -10, 10).unwrap();
+    }
+}
@@ -1,15 +1,36 @@
-mod util;
-
-use anyhow::Result;
-use plonky2::field::types::{Field, PrimeField64};
+use anyhow::{ensure, Result};
+use clap::Parser;
+use head_tail_check::{build_head_tail_circuit, range_bits, set_head_tail_witness};
 use plonky2::iop::witness::PartialWitness;
 use plonky2::plonk::circuit_builder::CircuitBuilder;
 use plonky2::plonk::circuit_data::CircuitConfig;
 use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+use range_check::commitment::commitment;
+use range_check_signed::{decode_i64_centered, encode_i64_slice};
+use range_check_input::{read_vector, Format};
+use std::path::PathBuf;
 use std::time::Instant;
-use plonky2::iop::target::Target;
+
+/// Proves that every element of a signed vector lies in [lower, upper] with the head-tail argument.
+#[derive(Parser, Debug)]
+struct Args {
+    /// The vector to range check.
+    #[arg(long, default_value = "resources/vec-head-tail-mini.txt")]
+    input: PathBuf,
+    /// The public lower bound (inclusive).
+    #[arg(long, default_value_t = -10, allow_negative_numbers = true)]
+    lower: i64,
+    /// The public upper bound (inclusive).
+    #[arg(long, default_value_t = 10, allow_negative_numbers = true)]
+    upper: i64,
+    /// Format of the input files (detected from the extension and contents by default).
+    #[arg(long, value_enum)]
+    format: Option<Format>,
+}
 
 fn main() -> Result<()> {
+    let args = Args::parse();
+
     //
     // STEP1: boilerplate code of circuit setup and data preparation
     const D: usize = 2;
@@ -21,54 +42,16 @@ fn main() -> Result<()> {
     let config: CircuitConfig = CircuitConfig::standard_recursion_config();
     let mut builder = CircuitBuilder::<F, D>::new(config);
 
-    // todo: set target later so the range is part of the public statement
-    let range = builder.add_virtual_target();
-    let field_modulus: u64 = 18446744069414584321; // (1u64 << 64) - (1u64 << 32) + 1;
-
-    // load data from file and load them onto Goldilocks field
-    let vec: Vec<i64> = util::read_vector_from_file("resources/vec1.txt")?;
-    let vec_field: Vec<F> = vec.iter().map(|&x| {
-        if x >= 0 {
-            F::from_canonical_u64(x as u64)
-        } else {
-
-            F::from_canonical_u64((field_modulus as i64 + x) as u64)
-        }
-    }).collect();
-
-    // Bound values
-    // todo convert target into number
-    let lower_bound = -10i64;
-    let upper_bound = 10i64;
-
-    // Convert valid values to field elements
-    let valid_values: Vec<F> = (lower_bound..=upper_bound)
-        .map(|x| {
-            if x >= 0 {
-                F::from_canonical_u64(x as u64)
-            } else {
-                F::from_canonical_u64((field_modulus as i64 + x) as u64)
-            }
-        })
-        .collect();
-
-    let valid_value_targets: Vec<Target> = valid_values.iter().map(|&x| builder.constant(x)).collect();
+    // load data from file, set_head_tail_witness encodes them onto the Goldilocks field
+    let vec: Vec<i64> = read_vector(&args.input, args.format)?;
 
     // STEP2: Build the circuit by adding constraints to check the range
-    for &value in &vec_field {
-        let value_target = builder.constant(value);
-        let mut any_valid = builder.zero();
-        let one = builder.one();
-        let zero  = builder.zero();
-        
-        for &valid_target in &valid_value_targets {
-            let is_equal = builder.is_equal(value_target, valid_target);
-            let is_valid = builder.select(is_equal, one, zero);
-            any_valid = builder.add(any_valid, is_valid);
-        }
-
-        builder.connect(any_valid, one);
-    }
+    // The values are private targets assigned through the PartialWitness, so the circuit
+    // shape only depends on the vector length and the data never becomes a circuit constant.
+    // Each value is checked by decomposing (x - lower) and (upper - x) into as many bits as
+    // upper - lower
+    let num_bits = range_bits(args.lower, args.upper)?;
+    let targets = build_head_tail_circuit(&mut builder, vec.len(), num_bits);
 
     // boilerplate code for benchmark, prove and verify
     let start_build = Instant::now();
@@ -76,13 +59,27 @@ fn main() -> Result<()> {
     let build_duration = start_build.elapsed();
     println!("Circuit built in: {:?}", build_duration);
 
+    let mut pw = PartialWitness::new();
+    set_head_tail_witness(&mut pw, &targets, &vec, args.lower, args.upper)?;
+
     let start_prove = Instant::now();
-    let pw = PartialWitness::new();
     let proof = data.prove(pw)?;
     let prove_duration = start_prove.elapsed();
     println!("Proof generated in: {:?}", prove_duration);
 
-    println!("Permutation proof generated successfully.");
+    println!("Head-tail proof generated successfully.");
+
+    // The proof commits to the private values, a verifier holding them recomputes the digest
+    ensure!(
+        proof.public_inputs[2..] == commitment::<F>(&encode_i64_slice(&vec)).elements,
+        "proof doesn't commit to the values"
+    );
+    // The bounds are the public inputs of the proof
+    println!(
+        "Public range: [{}, {}]",
+        decode_i64_centered(proof.public_inputs[0]),
+        decode_i64_centered(proof.public_inputs[1])
+    );
 
     let start_verify = Instant::now();
     data.verify(proof)?;
@@ -0,0 +1,284 @@
+//! Loading of the input vectors of the range-check binaries.
+//!
+//! A vector can be stored as
+//!   - comma-separated text, e.g. `4,1,2` (the output of `resources/gen.py`)
+//!   - whitespace-separated text, e.g. `4 1 2` or one value per line
+//!   - a JSON array, e.g. `[4, 1, 2]`
+//!   - binary, as consecutive 8-byte little-endian integers
+//!
+//! Unlike the old per-crate `read_vector_from_file`, a value that doesn't parse is an error
+//! reporting where it is rather than being silently dropped.
+
+use clap::ValueEnum;
+use serde::de::DeserializeOwned;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::num::ParseIntError;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// The encoding of a vector file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// Comma-separated values, possibly spread over several lines.
+    Csv,
+    /// Values separated by spaces, tabs or newlines.
+    Whitespace,
+    /// A JSON array of integers.
+    Json,
+    /// Consecutive 8-byte little-endian integers.
+    Binary,
+}
+
+impl Format {
+    /// Guesses the format of a file from its extension: `.json` and `.bin` files are JSON and
+    /// binary, anything else is text, comma-separated if it contains a comma.
+    pub fn detect(path: &Path, contents: &[u8]) -> Format {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Format::Json,
+            Some("bin") => Format::Binary,
+            _ if contents.contains(&b',') => Format::Csv,
+            _ => Format::Whitespace,
+        }
+    }
+}
+
+/// An integer type a vector can be read into.
+pub trait Element: FromStr<Err = ParseIntError> + DeserializeOwned {
+    const NAME: &'static str;
+
+    /// Decodes an 8-byte little-endian word, or `None` if it doesn't fit the type.
+    fn from_le_bytes(bytes: [u8; 8]) -> Option<Self>;
+}
+
+impl Element for u64 {
+    const NAME: &'static str = "u64";
+
+    fn from_le_bytes(bytes: [u8; 8]) -> Option<Self> {
+        Some(u64::from_le_bytes(bytes))
+    }
+}
+
+impl Element for i64 {
+    const NAME: &'static str = "i64";
+
+    fn from_le_bytes(bytes: [u8; 8]) -> Option<Self> {
+        Some(i64::from_le_bytes(bytes))
+    }
+}
+
+impl Element for usize {
+    const NAME: &'static str = "usize";
+
+    fn from_le_bytes(bytes: [u8; 8]) -> Option<Self> {
+        usize::try_from(u64::from_le_bytes(bytes)).ok()
+    }
+}
+
+/// The error of reading a vector.
+#[derive(Debug)]
+pub enum ReadError {
+    /// The file couldn't be read.
+    Io { path: PathBuf, source: io::Error },
+    /// A text file isn't valid UTF-8.
+    Utf8(std::str::Utf8Error),
+    /// The `position`-th value of a line isn't a valid integer of the requested type.
+    Parse {
+        line: usize,
+        position: usize,
+        value: String,
+        ty: &'static str,
+        source: ParseIntError,
+    },
+    /// The JSON isn't an array of integers of the requested type.
+    Json(serde_json::Error),
+    /// The length of a binary file isn't a multiple of 8 bytes.
+    BinaryLength(usize),
+    /// A binary value doesn't fit the requested type.
+    BinaryValue { index: usize, ty: &'static str },
+    /// A parse error, located in a file.
+    InFile {
+        path: PathBuf,
+        source: Box<ReadError>,
+    },
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadError::Io { path, source } => {
+                write!(f, "cannot read {}: {}", path.display(), source)
+            }
+            ReadError::Utf8(source) => write!(f, "invalid text vector: {}", source),
+            ReadError::Parse {
+                line,
+                position,
+                value,
+                ty,
+                source,
+            } => write!(
+                f,
+                "line {}, value {}: {:?} is not a valid {}: {}",
+                line, position, value, ty, source
+            ),
+            ReadError::Json(source) => write!(f, "invalid JSON vector: {}", source),
+            ReadError::BinaryLength(len) => write!(
+                f,
+                "binary vector of {} bytes is not a whole number of 8-byte values",
+                len
+            ),
+            ReadError::BinaryValue { index, ty } => {
+                write!(f, "value {} does not fit in {}", index, ty)
+            }
+            ReadError::InFile { path, source } => write!(f, "{}: {}", path.display(), source),
+        }
+    }
+}
+
+impl std::error::Error for ReadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReadError::Io { source, .. } => Some(source),
+            ReadError::Utf8(source) => Some(source),
+            ReadError::Parse { source, .. } => Some(source),
+            ReadError::Json(source) => Some(source),
+            ReadError::InFile { source, .. } => Some(source.as_ref()),
+            ReadError::BinaryLength(_) | ReadError::BinaryValue { .. } => None,
+        }
+    }
+}
+
+/// Reads a vector from a file, detecting its format with [`Format::detect`] unless one is given.
+pub fn read_vector<T: Element>(
+    path: impl AsRef<Path>,
+    format: Option<Format>,
+) -> Result<Vec<T>, ReadError> {
+    let path = path.as_ref();
+    let contents = fs::read(path).map_err(|source| ReadError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let format = format.unwrap_or_else(|| Format::detect(path, &contents));
+    parse_vector(&contents, format).map_err(|source| ReadError::InFile {
+        path: path.to_path_buf(),
+        source: Box::new(source),
+    })
+}
+
+/// Parses a vector from the contents of a file.
+pub fn parse_vector<T: Element>(contents: &[u8], format: Format) -> Result<Vec<T>, ReadError> {
+    match format {
+        Format::Csv => parse_text(contents, Some(',')),
+        Format::Whitespace => parse_text(contents, None),
+        Format::Json => serde_json::from_slice(contents).map_err(ReadError::Json),
+        Format::Binary => parse_binary(contents),
+    }
+}
+
+fn parse_text<T: Element>(contents: &[u8], separator: Option<char>) -> Result<Vec<T>, ReadError> {
+    let text = std::str::from_utf8(contents).map_err(ReadError::Utf8)?;
+    let mut vector = Vec::new();
+    for (line_index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let values: Vec<&str> = match separator {
+            // Tolerate a trailing separator, as written by many tools
+            Some(separator) => line.trim_end_matches(separator).split(separator).collect(),
+            None => line.split_whitespace().collect(),
+        };
+        for (value_index, value) in values.into_iter().enumerate() {
+            let value = value.trim();
+            let parsed = value.parse::<T>().map_err(|source| ReadError::Parse {
+                line: line_index + 1,
+                position: value_index + 1,
+                value: value.to_string(),
+                ty: T::NAME,
+                source,
+            })?;
+            vector.push(parsed);
+        }
+    }
+    Ok(vector)
+}
+
+fn parse_binary<T: Element>(contents: &[u8]) -> Result<Vec<T>, ReadError> {
+    let chunks = contents.chunks_exact(8);
+    if !chunks.remainder().is_empty() {
+        return Err(ReadError::BinaryLength(contents.len()));
+    }
+    chunks
+        .enumerate()
+        .map(|(index, chunk)| {
+            let bytes = chunk.try_into().expect("chunks are 8 bytes long");
+            T::from_le_bytes(bytes).ok_or(ReadError::BinaryValue { index, ty: T::NAME })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv() {
+        let vector: Vec<u64> = parse_vector(b"4,1, 2\n\n15,0,\n", Format::Csv).unwrap();
+        assert_eq!(vector, vec![4, 1, 2, 15, 0]);
+    }
+
+    #[test]
+    fn test_whitespace() {
+        let vector: Vec<i64> = parse_vector(b"-10 -9\t0\n10\n", Format::Whitespace).unwrap();
+        assert_eq!(vector, vec![-10, -9, 0, 10]);
+    }
+
+    #[test]
+    fn test_json() {
+        let vector: Vec<usize> = parse_vector(b"[4, 1, 2]", Format::Json).unwrap();
+        assert_eq!(vector, vec![4, 1, 2]);
+    }
+
+    #[test]
+    fn test_binary() {
+        let contents: Vec<u8> = [4i64, -1, 2].iter().flat_map(|x| x.to_le_bytes()).collect();
+        let vector: Vec<i64> = parse_vector(&contents, Format::Binary).unwrap();
+        assert_eq!(vector, vec![4, -1, 2]);
+
+        let err = parse_vector::<u64>(&contents[..7], Format::Binary).unwrap_err();
+        assert!(matches!(err, ReadError::BinaryLength(7)));
+    }
+
+    #[test]
+    fn test_invalid_value_is_reported() {
+        let err = parse_vector::<u64>(b"4,1\n2,-3", Format::Csv).unwrap_err();
+        match err {
+            ReadError::Parse {
+                line,
+                position,
+                value,
+                ty,
+                ..
+            } => assert_eq!((line, position, value.as_str(), ty), (2, 2, "-3", "u64")),
+            err => panic!("unexpected error: {}", err),
+        }
+
+        assert!(matches!(
+            parse_vector::<u64>(b"[4, -1]", Format::Json),
+            Err(ReadError::Json(_))
+        ));
+    }
+
+    #[test]
+    fn test_read_resources() {
+        let resources = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../resources");
+        let vec1: Vec<u64> = read_vector(resources.join("vec1.txt"), None).unwrap();
+        assert_eq!(vec1, vec![4, 1, 2]);
+        let signed: Vec<i64> = read_vector(resources.join("vec-head-tail-mini.txt"), None).unwrap();
+        assert_eq!(signed, (-10..=10).collect::<Vec<_>>());
+
+        let err = read_vector::<u64>(resources.join("gen_1000.txt"), None).unwrap_err();
+        assert!(matches!(err, ReadError::Io { .. }));
+    }
+}
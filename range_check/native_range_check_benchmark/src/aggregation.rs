@@ -0,0 +1,204 @@
+//! Range check of a vector too large for one circuit, split into tiles whose proofs are
+//! aggregated with recursion.
+//!
+//! Every tile circuit is a `build_range_check_circuit` of `tile_len` values, exposing the Poseidon
+//! commitment to the tile as its public inputs. Tile proofs are then verified pairwise by node circuits, each
+//! exposing the digest of its two children's digests, until a single root proof is left. When a
+//! level has an odd number of proofs the last one is paired with the zero digest, which no tile
+//! commitment hashes to: pairing it with itself instead would give [A, B, C] and [A, B, C, C] the same
+//! root. The public inputs of the root proof are thus a commitment to all tiles and their number,
+//! which `tiles_digest` recomputes natively.
+
+use crate::build_range_check_circuit;
+use anyhow::{ensure, Result};
+use plonky2::field::types::Field;
+use plonky2::hash::hash_types::HashOut;
+use plonky2::iop::target::{BoolTarget, Target};
+use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::{CircuitConfig, CircuitData};
+use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+use plonky2::plonk::proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget};
+use range_check::commitment::{commitment, register_commitment};
+
+pub const D: usize = 2;
+pub type C = PoseidonGoldilocksConfig;
+pub type F = <C as GenericConfig<D>>::F;
+
+/// A circuit verifying two proofs of the level below.
+struct NodeCircuit {
+    data: CircuitData<F, C, D>,
+    left: ProofWithPublicInputsTarget<D>,
+    right: ProofWithPublicInputsTarget<D>,
+    /// Whether the right child is padding: its proof is then a copy of the left one and the zero
+    /// digest replaces its public inputs.
+    padded: BoolTarget,
+}
+
+/// The circuits range checking `num_tiles` tiles of `tile_len` values and aggregating their proofs.
+pub struct TiledRangeCheck {
+    tile_len: usize,
+    num_tiles: usize,
+    tile_data: CircuitData<F, C, D>,
+    tile_targets: Vec<Target>,
+    nodes: Vec<NodeCircuit>,
+}
+
+impl TiledRangeCheck {
+    /// Builds the tile circuit, checking values lie in [0, 2^log_max), and one node circuit per
+    /// level of the aggregation tree.
+    pub fn new(num_tiles: usize, tile_len: usize, log_max: usize) -> Self {
+        assert!(num_tiles > 0, "there must be at least one tile");
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let tile_targets = build_range_check_circuit(&mut builder, tile_len, log_max);
+        let tile_data = builder.build::<C>();
+
+        let mut nodes: Vec<NodeCircuit> = Vec::new();
+        let mut width = num_tiles;
+        while width > 1 {
+            let inner = nodes.last().map_or(&tile_data, |node| &node.data);
+            nodes.push(build_node_circuit(inner));
+            width = width.div_ceil(2);
+        }
+
+        TiledRangeCheck {
+            tile_len,
+            num_tiles,
+            tile_data,
+            tile_targets,
+            nodes,
+        }
+    }
+
+    /// Proves every tile of `values` is in range, returning the root proof of the aggregation tree.
+    pub fn prove(&self, values: &[usize]) -> Result<ProofWithPublicInputs<F, C, D>> {
+        ensure!(
+            values.len() == self.num_tiles * self.tile_len,
+            "expected {} tiles of {} values, got {} values",
+            self.num_tiles,
+            self.tile_len,
+            values.len()
+        );
+
+        let mut proofs = values
+            .chunks(self.tile_len)
+            .map(|tile| {
+                let mut pw = PartialWitness::new();
+                for (&target, &value) in self.tile_targets.iter().zip(tile) {
+                    pw.set_target(target, F::from_canonical_usize(value));
+                }
+                self.tile_data.prove(pw)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        for node in &self.nodes {
+            proofs = proofs
+                .chunks(2)
+                .map(|pair| {
+                    let right = pair.last().expect("chunks are not empty");
+                    let mut pw = PartialWitness::new();
+                    pw.set_proof_with_pis_target(&node.left, &pair[0]);
+                    pw.set_proof_with_pis_target(&node.right, right);
+                    pw.set_bool_target(node.padded, pair.len() == 1);
+                    node.data.prove(pw)
+                })
+                .collect::<Result<Vec<_>>>()?;
+        }
+
+        Ok(proofs.pop().expect("the tree has a root"))
+    }
+
+    /// Verifies a root proof.
+    pub fn verify(&self, proof: ProofWithPublicInputs<F, C, D>) -> Result<()> {
+        self.root_data().verify(proof)
+    }
+
+    /// Returns the circuit the root proof belongs to.
+    pub fn root_data(&self) -> &CircuitData<F, C, D> {
+        self.nodes.last().map_or(&self.tile_data, |node| &node.data)
+    }
+}
+
+fn build_node_circuit(inner: &CircuitData<F, C, D>) -> NodeCircuit {
+    let config = CircuitConfig::standard_recursion_config();
+    let mut builder = CircuitBuilder::<F, D>::new(config);
+
+    let verifier_data = builder.constant_verifier_data(&inner.verifier_only);
+    let left = builder.add_virtual_proof_with_pis(&inner.common);
+    let right = builder.add_virtual_proof_with_pis(&inner.common);
+    builder.verify_proof::<C>(&left, &verifier_data, &inner.common);
+    builder.verify_proof::<C>(&right, &verifier_data, &inner.common);
+
+    let padded = builder.add_virtual_bool_target_safe();
+    let zero = builder.zero();
+    let right_digest: Vec<Target> = right
+        .public_inputs
+        .iter()
+        .map(|&x| builder.select(padded, zero, x))
+        .collect();
+    let children = [left.public_inputs.as_slice(), right_digest.as_slice()].concat();
+    register_commitment(&mut builder, &children);
+
+    NodeCircuit {
+        data: builder.build::<C>(),
+        left,
+        right,
+        padded,
+    }
+}
+
+/// Computes natively the commitment to the tiles exposed by the root proof.
+pub fn tiles_digest(values: &[usize], tile_len: usize) -> HashOut<F> {
+    let mut digests: Vec<HashOut<F>> = values
+        .chunks(tile_len)
+        .map(|tile| {
+            let tile: Vec<F> = tile.iter().map(|&x| F::from_canonical_usize(x)).collect();
+            commitment(&tile)
+        })
+        .collect();
+
+    while digests.len() > 1 {
+        digests = digests
+            .chunks(2)
+            .map(|pair| {
+                let right = pair.get(1).unwrap_or(&HashOut::ZERO);
+                commitment(&[pair[0].elements, right.elements].concat())
+            })
+            .collect();
+    }
+    digests[0]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aggregated_tiles() -> Result<()> {
+        // An odd number of tiles, so the last proof of the first level is paired with padding
+        let values: Vec<usize> = (0..24).map(|i| i * 5 % 64).collect();
+        let circuits = TiledRangeCheck::new(3, 8, 6);
+        let proof = circuits.prove(&values)?;
+        ensure!(proof.public_inputs == tiles_digest(&values, 8).elements.to_vec());
+        circuits.verify(proof)
+    }
+
+    #[test]
+    fn test_odd_tile_count_is_bound() {
+        // Duplicating the last of an odd number of tiles changes the root
+        let values: Vec<usize> = (0..24).map(|i| i * 5 % 64).collect();
+        let duplicated = [&values[..], &values[16..]].concat();
+        assert_ne!(tiles_digest(&values, 8), tiles_digest(&duplicated, 8));
+    }
+
+    #[test]
+    #[should_panic(expected = "was set twice with different values")]
+    fn test_tile_value_out_of_range() {
+        let mut values: Vec<usize> = vec![1; 8];
+        values[3] = 64;
+        let circuits = TiledRangeCheck::new(1, 8, 6);
+        circuits.prove(&values).unwrap();
+    }
+}
@@ -0,0 +1,44 @@
+pub mod aggregation;
+
+use anyhow::{ensure, Result};
+use plonky2::field::extension::Extendable;
+use plonky2::hash::hash_types::RichField;
+use plonky2::iop::target::Target;
+use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use range_check::commitment::register_commitment;
+
+/// Builds a circuit proving that each of `len` private values lies in [0, 2^log_max), using
+/// plonky2's built-in `range_check` gadget.
+///
+/// The public inputs are the Poseidon commitment to the values, see `range_check::commitment`.
+pub fn build_range_check_circuit<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    len: usize,
+    log_max: usize,
+) -> Vec<Target> {
+    let targets = builder.add_virtual_targets(len);
+    for &target in &targets {
+        builder.range_check(target, log_max);
+    }
+    register_commitment(builder, &targets);
+    targets
+}
+
+/// Assigns the values to a range-check circuit.
+pub fn set_range_check_witness<F: RichField>(
+    pw: &mut PartialWitness<F>,
+    targets: &[Target],
+    values: &[usize],
+) -> Result<()> {
+    ensure!(
+        values.len() == targets.len(),
+        "circuit expects a vector of length {}, got {}",
+        targets.len(),
+        values.len()
+    );
+    for (&target, &val) in targets.iter().zip(values) {
+        pw.set_target(target, F::from_canonical_usize(val));
+    }
+    Ok(())
+}
@@ -1,39 +1,54 @@
-mod util;
-
-use anyhow::Result;
-use plonky2::field::types::Field;
-use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+use anyhow::{ensure, Result};
+use clap::Parser;
+use native_range_check_benchmark::aggregation::{tiles_digest, TiledRangeCheck};
+use native_range_check_benchmark::{build_range_check_circuit, set_range_check_witness};
+use plonky2::iop::witness::PartialWitness;
 use plonky2::plonk::circuit_builder::CircuitBuilder;
 use plonky2::plonk::circuit_data::CircuitConfig;
 use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+use plonky2::field::types::Field;
+use range_check::commitment::commitment;
+use range_check_input::{read_vector, Format};
+use std::path::PathBuf;
 use std::time::Instant;
 
+/// Proves that every element of a vector lies in [0, 2^log_max) with plonky2's range check.
+#[derive(Parser, Debug)]
+struct Args {
+    /// The vector to range check.
+    #[arg(long, default_value = "resources/vec1.txt")]
+    input: PathBuf,
+    /// Split the vector into tiles of this many values, proved separately and aggregated with
+    /// recursion into a single proof committing to all tiles.
+    #[arg(long)]
+    tile_len: Option<usize>,
+    /// Format of the input files (detected from the extension and contents by default).
+    #[arg(long, value_enum)]
+    format: Option<Format>,
+}
+
 /// An example of using Plonky2 to prove that given values lie in a given range.
 fn main() -> Result<()> {
+    let args = Args::parse();
+
     const D: usize = 2;
     type C = PoseidonGoldilocksConfig;
     type F = <C as GenericConfig<D>>::F;
 
-    let config = CircuitConfig::standard_recursion_config();
-    let mut builder = CircuitBuilder::<F, D>::new(config);
-
     // The list of secret values.
-    let values: Vec<usize> = util::read_vector_from_file("resources/gen_1000.txt")?;
+    let values: Vec<usize> = read_vector(&args.input, args.format)?;
 
     let log_max = 6;
-    let mut targets = Vec::new();
-
-    for &val in &values {
-        let target = builder.add_virtual_target();
-        builder.register_public_input(target);
-        builder.range_check(target, log_max);
-        targets.push((target, val));
+    if let Some(tile_len) = args.tile_len {
+        return prove_tiled(&values, tile_len, log_max);
     }
 
+    let config = CircuitConfig::standard_recursion_config();
+    let mut builder = CircuitBuilder::<F, D>::new(config);
+    let targets = build_range_check_circuit(&mut builder, values.len(), log_max);
+
     let mut pw = PartialWitness::new();
-    for &(target, val) in &targets {
-        pw.set_target(target, F::from_canonical_usize(val));
-    }
+    set_range_check_witness(&mut pw, &targets, &values)?;
 
     // Boilerplate code for benchmark, prove and verify
     let start_build = Instant::now();
@@ -46,7 +61,14 @@ fn main() -> Result<()> {
     let prove_duration = start_prove.elapsed();
     println!("Proof generated in: {:?}", prove_duration);
 
-    println!("Permutation proof generated successfully.");
+    println!("Range-check proof generated successfully.");
+
+    // The proof commits to the private values, a verifier holding them recomputes the digest
+    let values_field: Vec<F> = values.iter().map(|&x| F::from_canonical_usize(x)).collect();
+    ensure!(
+        proof.public_inputs == commitment(&values_field).elements,
+        "proof doesn't commit to the values"
+    );
 
     let start_verify = Instant::now();
     data.verify(proof)?;
@@ -57,3 +79,42 @@ fn main() -> Result<()> {
 
     Ok(())
 }
+
+/// Range checks the values tile by tile and aggregates the tile proofs.
+fn prove_tiled(values: &[usize], tile_len: usize, log_max: usize) -> Result<()> {
+    ensure!(
+        tile_len > 0 && values.len().is_multiple_of(tile_len),
+        "{} values cannot be split into tiles of {}",
+        values.len(),
+        tile_len
+    );
+    let num_tiles = values.len() / tile_len;
+
+    let start_build = Instant::now();
+    let circuits = TiledRangeCheck::new(num_tiles, tile_len, log_max);
+    let build_duration = start_build.elapsed();
+    println!("Circuits for {} tiles built in: {:?}", num_tiles, build_duration);
+
+    let start_prove = Instant::now();
+    let proof = circuits.prove(values)?;
+    let prove_duration = start_prove.elapsed();
+    println!("Proof generated in: {:?}", prove_duration);
+
+    println!("Aggregated range-check proof generated successfully.");
+
+    // The root proof commits to the digest of all tiles
+    ensure!(
+        proof.public_inputs == tiles_digest(values, tile_len).elements.to_vec(),
+        "root proof doesn't commit to the tiles"
+    );
+    println!("Tiles digest: {:?}", proof.public_inputs);
+
+    let start_verify = Instant::now();
+    circuits.verify(proof)?;
+    let verify_duration = start_verify.elapsed();
+    println!("Proof verified in: {:?}", verify_duration);
+
+    println!("Proof verified successfully.");
+
+    Ok(())
+}
@@ -0,0 +1,65 @@
+//! Poseidon commitment to the private vector of a circuit.
+//!
+//! A range-check proof on its own says "I know some vector in range". Registering the digest of
+//! the vector as public input ties the proof to one specific image: a verifier holding the pixels
+//! recomputes the digest with `commitment` and compares it with the proof's public inputs.
+
+use plonky2::field::extension::Extendable;
+use plonky2::hash::hash_types::{HashOut, HashOutTarget, RichField};
+use plonky2::hash::poseidon::PoseidonHash;
+use plonky2::iop::target::Target;
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::config::Hasher;
+
+/// Hashes `values` in-circuit and registers the 4 elements of the digest as public inputs.
+pub fn register_commitment<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    values: &[Target],
+) -> HashOutTarget {
+    let digest = builder.hash_n_to_hash_no_pad::<PoseidonHash>(values.to_vec());
+    builder.register_public_inputs(&digest.elements);
+    digest
+}
+
+/// Computes natively the digest `register_commitment` exposes.
+pub fn commitment<F: RichField>(values: &[F]) -> HashOut<F> {
+    PoseidonHash::hash_no_pad(values)
+}
+
+/// Computes natively the digest of a vector of unsigned values, e.g. pixels.
+pub fn commitment_u64<F: RichField>(values: &[u64]) -> HashOut<F> {
+    let values: Vec<F> = values.iter().map(|&x| F::from_canonical_u64(x)).collect();
+    commitment(&values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plonky2::field::types::Field;
+    use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+    use plonky2::plonk::circuit_data::CircuitConfig;
+    use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_matches_native_commitment() -> anyhow::Result<()> {
+        let values = [4, 1, 2, 255, 0];
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let targets = builder.add_virtual_targets(values.len());
+        register_commitment(&mut builder, &targets);
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        for (&target, &x) in targets.iter().zip(&values) {
+            pw.set_target(target, F::from_canonical_u64(x));
+        }
+        let proof = data.prove(pw)?;
+        assert_eq!(proof.public_inputs, commitment_u64::<F>(&values).elements);
+        assert_ne!(proof.public_inputs, commitment_u64::<F>(&[4, 1, 2, 255, 1]).elements);
+        data.verify(proof)
+    }
+}
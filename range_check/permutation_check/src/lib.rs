@@ -0,0 +1,160 @@
+pub mod commitment;
+
+use anyhow::{ensure, Result};
+use plonky2::field::extension::Extendable;
+use plonky2::hash::hash_types::RichField;
+use plonky2::hash::poseidon::PoseidonHash;
+use plonky2::iop::target::Target;
+use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use commitment::register_commitment;
+
+/// The private targets of a permutation circuit, one per element of each vector.
+pub struct PermutationTargets {
+    pub vec1: Vec<Target>,
+    pub vec2: Vec<Target>,
+}
+
+/// Derives the Fiat-Shamir challenge of the permutation argument inside the circuit.
+///
+/// The challenge is the first element of the Poseidon digest of both vectors, so the prover
+/// cannot pick the vectors after seeing it.
+pub fn permutation_challenge<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    vec1: &[Target],
+    vec2: &[Target],
+) -> Target {
+    let inputs = [vec1, vec2].concat();
+    builder.hash_n_to_hash_no_pad::<PoseidonHash>(inputs).elements[0]
+}
+
+/// Returns a target holding product_{i}(x_i + gamma).
+pub fn grand_product<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    targets: &[Target],
+    gamma: Target,
+) -> Target {
+    let mut product = builder.one();
+    for &target in targets {
+        let term = builder.add(target, gamma);
+        product = builder.mul(product, term);
+    }
+    product
+}
+
+/// Constrains `vec2` to be a permutation of `vec1`.
+///
+/// We check the grand-product identity
+///           product_{i}(vec1_i + gamma) = product_{i}(vec2_i + gamma)
+/// where gamma is derived from both vectors by `permutation_challenge`. Both sides are polynomials
+/// in gamma whose roots are the negated elements, so they agree at a random gamma only if the two
+/// vectors hold the same multiset (except with probability n / |F|).
+pub fn assert_permutation<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    vec1: &[Target],
+    vec2: &[Target],
+) {
+    assert_eq!(
+        vec1.len(),
+        vec2.len(),
+        "a permutation must have the same length as the original vector"
+    );
+    let gamma = permutation_challenge(builder, vec1, vec2);
+    let product1 = grand_product(builder, vec1, gamma);
+    let product2 = grand_product(builder, vec2, gamma);
+    builder.connect(product1, product2);
+}
+
+/// Builds a circuit proving that two private vectors of length `len` are permutations of each
+/// other. The circuit shape only depends on `len`, so one circuit serves all inputs of that size.
+///
+/// The public inputs are the Poseidon commitment to `vec1`, see `commitment`.
+pub fn build_permutation_circuit<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    len: usize,
+) -> PermutationTargets {
+    let vec1 = builder.add_virtual_targets(len);
+    let vec2 = builder.add_virtual_targets(len);
+    assert_permutation(builder, &vec1, &vec2);
+    register_commitment(builder, &vec1);
+    PermutationTargets { vec1, vec2 }
+}
+
+/// Assigns the two vectors to the private targets of a permutation circuit.
+pub fn set_permutation_witness<F: RichField>(
+    pw: &mut PartialWitness<F>,
+    targets: &PermutationTargets,
+    vec1: &[u64],
+    vec2: &[u64],
+) -> Result<()> {
+    ensure!(
+        vec1.len() == targets.vec1.len() && vec2.len() == targets.vec2.len(),
+        "circuit expects vectors of length {}, got {} and {}",
+        targets.vec1.len(),
+        vec1.len(),
+        vec2.len()
+    );
+    for (&target, &x) in targets.vec1.iter().zip(vec1) {
+        pw.set_target(target, F::from_canonical_u64(x));
+    }
+    for (&target, &x) in targets.vec2.iter().zip(vec2) {
+        pw.set_target(target, F::from_canonical_u64(x));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plonky2::plonk::circuit_data::CircuitConfig;
+    use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    fn prove_and_verify(vec1: &[u64], vec2: &[u64]) -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let targets = build_permutation_circuit(&mut builder, vec1.len());
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        set_permutation_witness(&mut pw, &targets, vec1, vec2)?;
+        let proof = data.prove(pw)?;
+        ensure!(proof.public_inputs == commitment::commitment_u64::<F>(vec1).elements);
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_equal_multisets() -> Result<()> {
+        prove_and_verify(&[4, 1, 2, 2, 7], &[2, 7, 4, 2, 1])
+    }
+
+    #[test]
+    fn test_swapped_positions() -> Result<()> {
+        prove_and_verify(&[4, 1, 2, 2, 7], &[4, 7, 2, 2, 1])
+    }
+
+    #[test]
+    #[should_panic(expected = "was set twice with different values")]
+    fn test_single_swapped_element() {
+        // vec2 replaces a single element of vec1, so the multisets differ
+        prove_and_verify(&[4, 1, 2, 2, 7], &[4, 1, 3, 2, 7]).unwrap();
+    }
+
+    #[test]
+    fn test_different_lengths_witness() {
+        assert!(prove_and_verify(&[4, 1, 2], &[1, 4, 2, 2]).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "same length")]
+    fn test_different_lengths_circuit() {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let vec1 = builder.add_virtual_targets(3);
+        let vec2 = builder.add_virtual_targets(4);
+        assert_permutation(&mut builder, &vec1, &vec2);
+    }
+}
@@ -1,14 +1,32 @@
-mod util;
-
-use anyhow::Result;
-use plonky2::field::types::Field;
+use anyhow::{ensure, Result};
+use clap::Parser;
 use plonky2::iop::witness::PartialWitness;
 use plonky2::plonk::circuit_builder::CircuitBuilder;
 use plonky2::plonk::circuit_data::CircuitConfig;
 use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+use range_check::commitment::commitment_u64;
+use range_check::{build_permutation_circuit, set_permutation_witness};
+use range_check_input::{read_vector, Format};
+use std::path::PathBuf;
 use std::time::Instant;
 
+/// Proves that one private vector is a permutation of another.
+#[derive(Parser, Debug)]
+struct Args {
+    /// The original vector.
+    #[arg(long, default_value = "resources/vec1.txt")]
+    vec1: PathBuf,
+    /// The permuted vector.
+    #[arg(long, default_value = "resources/vec2.txt")]
+    vec2: PathBuf,
+    /// Format of the input files (detected from the extension and contents by default).
+    #[arg(long, value_enum)]
+    format: Option<Format>,
+}
+
 fn main() -> Result<()> {
+    let args = Args::parse();
+
     //
     // STEP1: circuit setup and data preparation
     const D: usize = 2;
@@ -20,56 +38,44 @@ fn main() -> Result<()> {
     let config: CircuitConfig = CircuitConfig::standard_recursion_config();
     let mut builder = CircuitBuilder::<F, D>::new(config);
 
-    let vec1: Vec<u64> = util::read_vector_from_file("resources/vec1.txt")?;
-    let vec1_field: Vec<F> = vec1.iter()
-                                                 .map(|&x| F::from_canonical_u64(x))
-                                                 .collect();
-    let vec2: Vec<u64> = util::read_vector_from_file("resources/vec2.txt")?;
-    let vec2_field: Vec<F> = vec2.iter()
-                                                 .map(|&x| F::from_canonical_u64(x))
-                                                 .collect();
+    let vec1: Vec<u64> = read_vector(&args.vec1, args.format)?;
+    let vec2: Vec<u64> = read_vector(&args.vec2, args.format)?;
+
+    ensure!(
+        vec1.len() == vec2.len(),
+        "vectors of different lengths cannot be permutations of each other"
+    );
 
     //
     // STEP2: build the circuit by adding constraints of checking permutation  
     // The public statement is "I know two vectors, one is the permutation of another"
-    // The secret witness is the vecs, which generate the proof and are hidden from public
-    // 
-    // NOTE: this is an inefficient implement of permutation check 
-                                                
-    // we check permutation by comparing (val, freq) pair
-    let count1 = util::count_elements(&vec1_field);
-    let count2 = util::count_elements(&vec2_field);
-
-    for (key, &value1) in &count1 {
-        let value2 = *count2.get(key).unwrap_or(&0);
-
-        let value1_field = builder.constant(F::from_canonical_usize(value1));
-        let value2_field = builder.constant(F::from_canonical_usize(value2));
-
-        builder.connect(value1_field, value2_field);
-    }
-    for (key, &value2) in &count2 {
-        let value1 = *count1.get(key).unwrap_or(&0);
-
-        let value1_field = builder.constant(F::from_canonical_usize(value1));
-        let value2_field = builder.constant(F::from_canonical_usize(value2));
-
-        builder.connect(value1_field, value2_field);
-    }
+    // The secret witness is the vecs, which are assigned through the PartialWitness and never
+    // become part of the circuit. The circuit shape only depends on the vector length.
+    //
+    // The permutation is checked in-circuit with a grand-product argument, see `assert_permutation`
+    let targets = build_permutation_circuit(&mut builder, vec1.len());
 
     let start_build = Instant::now();
     let data = builder.build::<C>();
     let build_duration = start_build.elapsed();
     println!("Circuit built in: {:?}", build_duration);
 
+    let mut pw = PartialWitness::new();
+    set_permutation_witness(&mut pw, &targets, &vec1, &vec2)?;
+
     let start_prove = Instant::now();
-    let pw = PartialWitness::new();
     let proof = data.prove(pw)?;
     let prove_duration = start_prove.elapsed();
     println!("Proof generated in: {:?}", prove_duration);
 
     println!("Permutation proof generated successfully.");
 
+    // The proof commits to the private values, a verifier holding them recomputes the digest
+    ensure!(
+        proof.public_inputs == commitment_u64::<F>(&vec1).elements,
+        "proof doesn't commit to the values"
+    );
+
     let start_verify = Instant::now();
     data.verify(proof)?;
     let verify_duration = start_verify.elapsed();
@@ -0,0 +1,11 @@
+pub mod logup;
+pub mod plookup;
+mod quotient;
+pub mod security;
+
+use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+pub const D: usize = 2;
+pub type C = PoseidonGoldilocksConfig; // PoseidonGoldilocksConfig provides poseidon hash function and the Goldilocks field.
+pub type F = <C as GenericConfig<D>>::F;
+pub type H = <C as GenericConfig<D>>::Hasher;
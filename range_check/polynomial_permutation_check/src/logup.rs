@@ -0,0 +1,404 @@
+use anyhow::{ensure, Context, Result};
+use core::cmp::max;
+use plonky2::field::extension::Extendable;
+use plonky2::field::fft::fft_root_table;
+use plonky2::field::polynomial::PolynomialValues;
+use plonky2::field::ops::Square;
+use plonky2::field::types::Field;
+use plonky2::fri::oracle::PolynomialBatch;
+use plonky2::fri::proof::FriProof;
+use plonky2::fri::structure::{
+    FriBatchInfo, FriInstanceInfo, FriOpeningBatch, FriOpenings, FriOracleInfo, FriPolynomialInfo,
+};
+use plonky2::fri::verifier::verify_fri_proof;
+use plonky2::fri::FriConfig;
+use plonky2::hash::merkle_tree::MerkleCap;
+use plonky2::iop::challenger::Challenger;
+use plonky2::util::log2_ceil;
+use plonky2::util::serialization::Write;
+use plonky2::util::timing::TimingTree;
+use plonky2_maybe_rayon::*;
+
+use crate::plookup::table_values;
+use crate::quotient::Coset;
+use crate::{C, D, F, H};
+
+type FE = <F as Extendable<D>>::Extension;
+
+// Oracle 0 holds the trace columns, oracle 1 the helper columns and the running sum, oracle 2 the
+// quotient of the combined constraints
+const TRACE: usize = 0;
+const ARGUMENT: usize = 1;
+const QUOTIENT: usize = 2;
+
+// Polynomials of the trace oracle
+const V: usize = 0;
+const M: usize = 1;
+const NUM_TRACE_POLYS: usize = 2;
+
+// Polynomials of the argument oracle
+const A: usize = 0;
+const B: usize = 1;
+const S: usize = 2;
+const NUM_ARGUMENT_POLYS: usize = 3;
+
+// Polynomials of the quotient oracle
+const Q: usize = 0;
+const NUM_QUOTIENT_POLYS: usize = 1;
+
+// Opening points, in the order of the FRI batches
+const ZETA: usize = 0;
+const ZETA_NEXT: usize = 1;
+
+/// A proof that every committed pixel lies in [0, 2^range_bits), using the LogUp lookup argument.
+///
+/// The values v are looked up in the table t = [0, 1, ..., PIXEL_RANGE - 1] padded with 0, with m
+/// the number of times each table entry is looked up (0 on the padding rows of the table). For a
+/// random gamma all values are in the table iff
+///           sum_{i=0}^{N-1} 1 / (gamma + v_i) = sum_{i=0}^{N-1} m_i / (gamma + t_i)
+/// Unlike the plookup argument there is no sorted column, so the domain only needs to fit the larger
+/// of the values and the table. The fractions are committed as the helper columns
+/// a = 1 / (gamma + v) and b = m / (gamma + t), and the running sum s of a - b must wrap around:
+///           s[omega*X] - s[X] = a[X] - b[X] on the whole domain
+/// which holds iff the sum of a - b over the domain is zero.
+#[derive(Debug)]
+pub struct LogUpProof {
+    /// log2 of the size N of the evaluation domain.
+    pub degree_bits: usize,
+    /// Merkle cap of the trace oracle: v, m.
+    pub trace_cap: MerkleCap<F, H>,
+    /// Merkle cap of the argument oracle: a, b, s.
+    pub argument_cap: MerkleCap<F, H>,
+    /// Merkle cap of the quotient oracle: q.
+    pub quotient_cap: MerkleCap<F, H>,
+    /// Evaluations of all polynomials at zeta, and of s at omega * zeta.
+    pub openings: FriOpenings<F, D>,
+    pub opening_proof: FriProof<F, H, D>,
+}
+
+impl LogUpProof {
+    /// Serializes the proof, mostly used to measure its size.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        for cap in [&self.trace_cap, &self.argument_cap, &self.quotient_cap] {
+            buffer
+                .write_merkle_cap(cap)
+                .expect("writing to a Vec cannot fail");
+        }
+        for batch in &self.openings.batches {
+            buffer
+                .write_field_ext_vec::<F, D>(&batch.values)
+                .expect("writing to a Vec cannot fail");
+        }
+        buffer
+            .write_fri_proof::<F, C, D>(&self.opening_proof)
+            .expect("writing to a Vec cannot fail");
+        buffer
+    }
+}
+
+/// The smallest domain size (as log2) that fits both the range table and the pixels.
+pub fn degree_bits(num_values: usize, range_bits: usize) -> usize {
+    log2_ceil(max(1 << range_bits, num_values))
+}
+
+/// Proves that every element of `values` lies in [0, 2^range_bits).
+pub fn prove(values: &[u64], range_bits: usize, fri_config: &FriConfig) -> Result<LogUpProof> {
+    ensure!(!values.is_empty(), "there are no values to range check");
+    let pixel_range = 1usize << range_bits;
+    let degree_bits = degree_bits(values.len(), range_bits);
+    let degree = 1 << degree_bits;
+    ensure!(
+        values.iter().all(|&x| x < pixel_range as u64),
+        "pixel values are out of range"
+    );
+
+    // The constraints have degree 2, so they fit on a domain twice as large as H
+    let quotient_bits = 1;
+    let max_fft_points = 1 << (degree_bits + fri_config.rate_bits);
+    let fft_root_table = fft_root_table(max_fft_points);
+
+    // v is the value read from the image, padded 0 up to len of `DEGREE`
+    let mut v_vals: Vec<F> = values.iter().map(|&x| F::from_canonical_u64(x)).collect();
+    v_vals.resize(degree, F::ZERO);
+
+    // m counts the lookups of each table entry, padding zeros included
+    let mut multiplicities = vec![0u64; degree];
+    multiplicities[0] = (degree - values.len()) as u64;
+    for &x in values {
+        multiplicities[x as usize] += 1;
+    }
+    let m_vals: Vec<F> = multiplicities.into_iter().map(F::from_canonical_u64).collect();
+
+    let t_vals = table_values(pixel_range, degree);
+    let v = PolynomialValues::new(v_vals.clone()).ifft();
+    let m = PolynomialValues::new(m_vals.clone()).ifft();
+    let t = PolynomialValues::new(t_vals.clone()).ifft();
+
+    // commit to v, m
+    let trace = PolynomialBatch::<F, C, D>::from_coeffs(
+        vec![v.clone(), m.clone()],
+        fri_config.rate_bits,
+        true,
+        fri_config.cap_height,
+        &mut TimingTree::default(),
+        Some(&fft_root_table),
+    );
+
+    let mut challenger = Challenger::<F, H>::new();
+    challenger.observe_cap::<H>(&trace.merkle_tree.cap);
+    let gamma = challenger.get_challenge();
+
+    // a = 1 / (gamma + v), b = m / (gamma + t) and s = [0, a_0 - b_0, a_0 - b_0 + a_1 - b_1, ...]
+    let shifted = |vals: &[F]| vals.iter().map(|&x| gamma + x).collect::<Vec<_>>();
+    let v_shifted = shifted(&v_vals);
+    let t_shifted = shifted(&t_vals);
+    ensure!(
+        v_shifted.iter().chain(&t_shifted).all(|x| x.is_nonzero()),
+        "gamma collides with a value"
+    );
+    let a_vals = F::batch_multiplicative_inverse(&v_shifted);
+    let b_vals: Vec<F> = F::batch_multiplicative_inverse(&t_shifted)
+        .into_iter()
+        .zip(&m_vals)
+        .map(|(inverse, &m)| inverse * m)
+        .collect();
+    let mut s_vals = Vec::with_capacity(degree);
+    let mut sum = F::ZERO;
+    for (&a, &b) in a_vals.iter().zip(&b_vals) {
+        s_vals.push(sum);
+        sum += a - b;
+    }
+    ensure!(sum == F::ZERO, "the lookups do not balance the table");
+
+    let a = PolynomialValues::new(a_vals).ifft();
+    let b = PolynomialValues::new(b_vals).ifft();
+    let s = PolynomialValues::new(s_vals).ifft();
+
+    let argument = PolynomialBatch::<F, C, D>::from_coeffs(
+        vec![a.clone(), b.clone(), s.clone()],
+        fri_config.rate_bits,
+        true,
+        fri_config.cap_height,
+        &mut TimingTree::default(),
+        Some(&fft_root_table),
+    );
+    challenger.observe_cap::<H>(&argument.merkle_tree.cap);
+    let alpha = challenger.get_challenge();
+
+    // q[X] = (a[X](gamma + v[X]) - 1 + alpha (b[X](gamma + t[X]) - m[X])
+    //         + alpha^2 (s[omega*X] - s[X] - a[X] + b[X])) / Z_H[X]
+    let coset = Coset::new(degree_bits, quotient_bits);
+    let [v_lde, m_lde, t_lde, a_lde, b_lde, s_lde] = [&v, &m, &t, &a, &b, &s].map(|p| coset.lde(p));
+    let s_next = coset.next(&s_lde);
+    let alpha_square = alpha.square();
+    let constraint = (0..coset.len())
+        .into_par_iter()
+        .map(|i| {
+            let c_a = a_lde[i] * (gamma + v_lde[i]) - F::ONE;
+            let c_b = b_lde[i] * (gamma + t_lde[i]) - m_lde[i];
+            let c_s = s_next[i] - s_lde[i] - a_lde[i] + b_lde[i];
+            c_a + alpha * c_b + alpha_square * c_s
+        })
+        .collect();
+    let q = coset
+        .quotient(constraint)
+        .context("LogUp constraints do not hold")?;
+
+    let quotient = PolynomialBatch::<F, C, D>::from_coeffs(
+        vec![q],
+        fri_config.rate_bits,
+        true,
+        fri_config.cap_height,
+        &mut TimingTree::default(),
+        Some(&fft_root_table),
+    );
+    challenger.observe_cap::<H>(&quotient.merkle_tree.cap);
+
+    let zeta = challenger.get_extension_challenge::<D>();
+    let instance = fri_instance(degree_bits, zeta);
+
+    let oracles = [&trace, &argument, &quotient];
+    let openings = FriOpenings {
+        batches: instance
+            .batches
+            .iter()
+            .map(|batch| FriOpeningBatch {
+                values: batch
+                    .polynomials
+                    .par_iter()
+                    .map(|p| {
+                        oracles[p.oracle_index].polynomials[p.polynomial_index]
+                            .to_extension::<D>()
+                            .eval(batch.point)
+                    })
+                    .collect(),
+            })
+            .collect(),
+    };
+    challenger.observe_openings(&openings);
+
+    let opening_proof = PolynomialBatch::<F, C, D>::prove_openings(
+        &instance,
+        &oracles,
+        &mut challenger,
+        &fri_config.fri_params(degree_bits, true),
+        &mut TimingTree::default(),
+    );
+
+    Ok(LogUpProof {
+        degree_bits,
+        trace_cap: trace.merkle_tree.cap.clone(),
+        argument_cap: argument.merkle_tree.cap.clone(),
+        quotient_cap: quotient.merkle_tree.cap.clone(),
+        openings,
+        opening_proof,
+    })
+}
+
+/// Verifies a proof that every committed pixel lies in [0, 2^range_bits).
+pub fn verify(proof: &LogUpProof, range_bits: usize, fri_config: &FriConfig) -> Result<()> {
+    let degree_bits = proof.degree_bits;
+    let degree = 1usize << degree_bits;
+    let pixel_range = 1usize << range_bits;
+    ensure!(pixel_range <= degree, "the domain is too small for the range table");
+
+    // Replay the prover's transcript
+    let mut challenger = Challenger::<F, H>::new();
+    challenger.observe_cap::<H>(&proof.trace_cap);
+    let gamma = challenger.get_challenge();
+    challenger.observe_cap::<H>(&proof.argument_cap);
+    let alpha = challenger.get_challenge();
+    challenger.observe_cap::<H>(&proof.quotient_cap);
+    let zeta = challenger.get_extension_challenge::<D>();
+    ensure!(
+        zeta.exp_u64(degree as u64) != FE::ONE,
+        "zeta must not lie in the evaluation domain"
+    );
+    let instance = fri_instance(degree_bits, zeta);
+    ensure!(
+        proof.openings.batches.len() == instance.batches.len()
+            && proof
+                .openings
+                .batches
+                .iter()
+                .zip(&instance.batches)
+                .all(|(opening, batch)| opening.values.len() == batch.polynomials.len()),
+        "the openings do not match the opened polynomials"
+    );
+    challenger.observe_openings(&proof.openings);
+
+    let fri_challenges = challenger.fri_challenges::<C, D>(
+        &proof.opening_proof.commit_phase_merkle_caps,
+        &proof.opening_proof.final_poly,
+        proof.opening_proof.pow_witness,
+        degree_bits,
+        fri_config,
+    );
+    verify_fri_proof::<F, C, D>(
+        &instance,
+        &proof.openings,
+        &fri_challenges,
+        &[
+            proof.trace_cap.clone(),
+            proof.argument_cap.clone(),
+            proof.quotient_cap.clone(),
+        ],
+        &proof.opening_proof,
+        &fri_config.fri_params(degree_bits, true),
+    )?;
+
+    let at_zeta = &proof.openings.batches[ZETA].values;
+    let trace = |poly: usize| at_zeta[poly];
+    let argument = |poly: usize| at_zeta[NUM_TRACE_POLYS + poly];
+    let quotient = |poly: usize| at_zeta[NUM_TRACE_POLYS + NUM_ARGUMENT_POLYS + poly];
+    let s_next = proof.openings.batches[ZETA_NEXT].values[0];
+
+    // t is public, so the verifier evaluates the range table on its own
+    let t_zeta = PolynomialValues::new(table_values(pixel_range, degree))
+        .ifft()
+        .to_extension::<D>()
+        .eval(zeta);
+
+    let gamma = FE::from(gamma);
+    let alpha = FE::from(alpha);
+    let vanishing_poly_zeta = zeta.exp_u64(degree as u64) - FE::ONE;
+    let c_a = argument(A) * (gamma + trace(V)) - FE::ONE;
+    let c_b = argument(B) * (gamma + t_zeta) - trace(M);
+    let c_s = s_next - argument(S) - argument(A) + argument(B);
+    ensure!(
+        c_a + alpha * c_b + alpha.square() * c_s == quotient(Q) * vanishing_poly_zeta,
+        "LogUp constraints do not hold"
+    );
+
+    Ok(())
+}
+
+/// All polynomials opened at zeta, and the running sum at omega * zeta.
+fn fri_instance(degree_bits: usize, zeta: FE) -> FriInstanceInfo<F, D> {
+    let g = FE::primitive_root_of_unity(degree_bits);
+    let all_polys = [
+        FriPolynomialInfo::from_range(TRACE, 0..NUM_TRACE_POLYS),
+        FriPolynomialInfo::from_range(ARGUMENT, 0..NUM_ARGUMENT_POLYS),
+        FriPolynomialInfo::from_range(QUOTIENT, 0..NUM_QUOTIENT_POLYS),
+    ]
+    .concat();
+    FriInstanceInfo {
+        oracles: [NUM_TRACE_POLYS, NUM_ARGUMENT_POLYS, NUM_QUOTIENT_POLYS]
+            .into_iter()
+            .map(|num_polys| FriOracleInfo {
+                num_polys,
+                blinding: true,
+            })
+            .collect(),
+        batches: vec![
+            FriBatchInfo {
+                point: zeta,
+                polynomials: all_polys,
+            },
+            FriBatchInfo {
+                point: g * zeta,
+                polynomials: FriPolynomialInfo::from_range(ARGUMENT, S..S + 1),
+            },
+        ],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::FriPreset;
+
+    fn fri_config() -> FriConfig {
+        FriPreset::default().config()
+    }
+
+    #[test]
+    fn test_values_in_range() -> Result<()> {
+        // More values than table entries, so the domain is sized by the values
+        let values: Vec<u64> = (0..40).map(|i| i * 7 % 32).collect();
+        let proof = prove(&values, 5, &fri_config())?;
+        assert_eq!(proof.degree_bits, 6);
+        verify(&proof, 5, &fri_config())
+    }
+
+    #[test]
+    fn test_value_out_of_range() {
+        assert!(prove(&[3, 32, 7], 5, &fri_config()).is_err());
+    }
+
+    #[test]
+    fn test_wrong_range() -> Result<()> {
+        let proof = prove(&[3, 17, 7], 5, &fri_config())?;
+        assert!(verify(&proof, 4, &fri_config()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_tampered_multiplicity() -> Result<()> {
+        let mut proof = prove(&[3, 17, 7], 5, &fri_config())?;
+        proof.openings.batches[ZETA].values[M] += FE::ONE;
+        assert!(verify(&proof, 5, &fri_config()).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,537 @@
+use anyhow::{ensure, Context, Result};
+use plonky2::field::extension::Extendable;
+use plonky2::field::fft::fft_root_table;
+use plonky2::field::polynomial::{PolynomialCoeffs, PolynomialValues};
+use plonky2::field::types::Field;
+use plonky2::fri::oracle::PolynomialBatch;
+use plonky2::fri::proof::FriProof;
+use plonky2::fri::structure::{
+    FriBatchInfo, FriInstanceInfo, FriOpeningBatch, FriOpenings, FriOracleInfo, FriPolynomialInfo,
+};
+use plonky2::fri::verifier::verify_fri_proof;
+use plonky2::fri::FriConfig;
+use plonky2::hash::merkle_tree::MerkleCap;
+use plonky2::iop::challenger::Challenger;
+use plonky2::util::serialization::Write;
+use plonky2::util::timing::TimingTree;
+use plonky2::util::log2_ceil;
+use plonky2_maybe_rayon::*;
+
+use crate::quotient::Coset;
+use crate::{C, D, F, H};
+
+type FE = <F as Extendable<D>>::Extension;
+
+// Oracle 0 holds the trace columns, oracle 1 the grand products and their quotients, both with the
+// degree bound of the FRI proof: fewer than N coefficients. Oracle 2 holds the quotient of the
+// adjacency constraint split in two halves with the degree bound N/2, along with the halves shifted by
+// X^(N/2): FRI bounds the shifted halves by N, which bounds the halves by N/2 once the verifier checks
+// the shifts at zeta.
+const TRACE: usize = 0;
+const ARGUMENT: usize = 1;
+const RANGE: usize = 2;
+
+// Polynomials of the trace oracle
+const W: usize = 0;
+const V: usize = 1;
+const Z: usize = 2;
+const NUM_TRACE_POLYS: usize = 3;
+
+// Polynomials of the argument oracle
+const W_PROD: usize = 0;
+const Q_W: usize = 1;
+const V_PROD: usize = 2;
+const Q_V: usize = 3;
+const Z_PROD: usize = 4;
+const Q_Z: usize = 5;
+const NUM_ARGUMENT_POLYS: usize = 6;
+
+// Polynomials of the range oracle
+const Q_RANGE_LO: usize = 0;
+const Q_RANGE_HI: usize = 1;
+const Q_RANGE_LO_SHIFTED: usize = 2;
+const Q_RANGE_HI_SHIFTED: usize = 3;
+const NUM_RANGE_POLYS: usize = 4;
+
+// Opening points, in the order of the FRI batches
+const ZETA: usize = 0;
+const ZETA_NEXT: usize = 1;
+const FIRST_ROW: usize = 2;
+const LAST_ROW: usize = 3;
+
+/// A proof that every committed pixel lies in [0, 2^range_bits), using the sort-and-adjacent-difference
+/// (Plookup-style) argument.
+///
+/// We want to prove:
+///           product_{i=0}^{N-1}(v_i + gamma) * product_{i=0}^{N-1}(w_i + gamma) = product_{i=0}^{N-1}(z_i + gamma) * gamma^N
+/// where v holds the image pixels, w is the range that the pixel values must lie in [0, PIXEL_RANGE-1],
+/// and z is the sorted concatentation of v and w, all padded with 0 up to the domain size N.
+/// Each product is accumulated in its own polynomial, and z must start at 0, end at PIXEL_RANGE - 1
+/// and only step by 0 or 1.
+#[derive(Debug)]
+pub struct RangeProof {
+    /// log2 of the size N of the evaluation domain.
+    pub degree_bits: usize,
+    /// Merkle cap of the trace oracle: w, v, z.
+    pub trace_cap: MerkleCap<F, H>,
+    /// Merkle cap of the argument oracle: w_prod, q_w, v_prod, q_v, z_prod, q_z.
+    pub argument_cap: MerkleCap<F, H>,
+    /// Merkle cap of the range oracle: q_range_lo, q_range_hi and their shifts by X^(N/2).
+    pub range_cap: MerkleCap<F, H>,
+    /// Evaluations of the trace and argument polynomials at zeta, omega * zeta, 1 and omega^(N-1),
+    /// and of the range oracle at zeta.
+    pub openings: FriOpenings<F, D>,
+    pub opening_proof: FriProof<F, H, D>,
+}
+
+impl RangeProof {
+    /// Serializes the proof, mostly used to measure its size.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        buffer
+            .write_merkle_cap(&self.trace_cap)
+            .expect("writing to a Vec cannot fail");
+        buffer
+            .write_merkle_cap(&self.argument_cap)
+            .expect("writing to a Vec cannot fail");
+        buffer
+            .write_merkle_cap(&self.range_cap)
+            .expect("writing to a Vec cannot fail");
+        for batch in &self.openings.batches {
+            buffer
+                .write_field_ext_vec::<F, D>(&batch.values)
+                .expect("writing to a Vec cannot fail");
+        }
+        buffer
+            .write_fri_proof::<F, C, D>(&self.opening_proof)
+            .expect("writing to a Vec cannot fail");
+        buffer
+    }
+}
+
+/// The smallest domain size (as log2) that fits both the range table and the pixels.
+pub fn degree_bits(num_values: usize, range_bits: usize) -> usize {
+    log2_ceil((1 << range_bits) + num_values)
+}
+
+/// Proves that every element of `values` lies in [0, 2^range_bits).
+pub fn prove(values: &[u64], range_bits: usize, fri_config: &FriConfig) -> Result<RangeProof> {
+    ensure!(!values.is_empty(), "there are no values to range check");
+    let pixel_range = 1usize << range_bits;
+    let degree_bits = degree_bits(values.len(), range_bits);
+    let degree = 1 << degree_bits;
+    ensure!(
+        values.iter().all(|&x| x < pixel_range as u64),
+        "pixel values are out of range"
+    );
+
+    // The constraints have degree 2, so their numerators fit on a domain twice as large as H
+    let quotient_bits = 1;
+    let max_fft_points = 1 << (degree_bits + fri_config.rate_bits);
+    // pre-compute  [1, ω, ω^2, ω^3, ..., ω^(max_fft_points-1)] table instead of computing on the fly
+    let fft_root_table = fft_root_table(max_fft_points);
+
+    // w is the range of the pixel, typically from 0 to 255. We pad w 0 up to len of `DEGREE`
+    // w_vals = [0, 1,...,PIXEL_RANGE - 1, 0, 0, ..., 0]
+    let w_vals = table_values(pixel_range, degree);
+
+    // v is the value read from the image, padded 0 up to len of `DEGREE`
+    let mut v_vals: Vec<F> = values.iter().map(|&x| F::from_canonical_u64(x)).collect();
+    v_vals.resize(degree, F::ZERO);
+
+    // z = sort(w || v), keeping only the DEGREE - PIXEL_RANGE - PIXELS padding zeros
+    let mut z_vals_u64: Vec<u64> = (0..pixel_range as u64).chain(values.iter().copied()).collect();
+    z_vals_u64.resize(degree, 0);
+    z_vals_u64.sort();
+    let z_vals: Vec<F> = z_vals_u64.into_iter().map(F::from_canonical_u64).collect();
+
+    let w = PolynomialValues::new(w_vals.clone()).ifft();
+    let v = PolynomialValues::new(v_vals.clone()).ifft();
+    let z = PolynomialValues::new(z_vals.clone()).ifft();
+
+    // commit to w, v, z
+    let trace = PolynomialBatch::<F, C, D>::from_coeffs(
+        vec![w.clone(), v.clone(), z.clone()],
+        fri_config.rate_bits,
+        true,
+        fri_config.cap_height,
+        &mut TimingTree::default(),
+        Some(&fft_root_table),
+    );
+
+    let mut challenger = Challenger::<F, H>::new();
+    challenger.observe_cap::<H>(&trace.merkle_tree.cap);
+    let gamma = challenger.get_challenge();
+
+    // The constraints are evaluated on a coset, where n_1(X) = ω^(DEGREE-1) - X excludes the
+    // wrap-around from the last row to the first
+    let coset = Coset::new(degree_bits, quotient_bits);
+    let omega = F::primitive_root_of_unity(degree_bits);
+    let last_row = omega.exp_u64((degree - 1) as u64);
+    let n_1: Vec<F> = coset.points().into_iter().map(|x| last_row - x).collect();
+
+    // For each column c, c_prod_vals = [1, (c_0 + gamma), [(c_0 + gamma)(c_1 + gamma)],...,[(c_0 + gamma)...(c_{N-2} + gamma)]]
+    // and the quotient proves c_prod[omega*X] = c_prod[X](gamma + c[X]) on every row but the last
+    let mut argument_polys = Vec::with_capacity(NUM_ARGUMENT_POLYS);
+    for (vals, col) in [(&w_vals, &w), (&v_vals, &v), (&z_vals, &z)] {
+        let prod = PolynomialValues::new(grand_product_values(vals, gamma)).ifft();
+        let prod_lde = coset.lde(&prod);
+        let constraint = coset
+            .next(&prod_lde)
+            .into_par_iter()
+            .zip(prod_lde)
+            .zip(coset.lde(col))
+            .zip(&n_1)
+            .map(|(((prod_next, prod), c), &n_1)| (prod_next - prod * (gamma + c)) * n_1)
+            .collect();
+        let q = coset
+            .quotient(constraint)
+            .context("grand product constraint does not hold")?;
+        argument_polys.push(prod); // prove that we construct the prod honestly
+        argument_polys.push(q);
+    }
+
+    let argument = PolynomialBatch::<F, C, D>::from_coeffs(
+        argument_polys,
+        fri_config.rate_bits,
+        true,
+        fri_config.cap_height,
+        &mut TimingTree::default(),
+        Some(&fft_root_table),
+    );
+    challenger.observe_cap::<H>(&argument.merkle_tree.cap);
+
+    // q_range[X] = (z[omega*X] - z[X])(1 - (z[omega*X] - z[X])) * n_1[X] / Z_H[X], whose numerator
+    // has degree 2(N - 1) + 1 like the grand product constraints, so q_range has up to N coefficients
+    let z_lde = coset.lde(&z);
+    let constraint = coset
+        .next(&z_lde)
+        .into_par_iter()
+        .zip(z_lde)
+        .zip(&n_1)
+        .map(|((z_next, z), &n_1)| {
+            let diff = z_next - z;
+            diff * (F::ONE - diff) * n_1
+        })
+        .collect();
+    let q_range = coset
+        .quotient(constraint)
+        .context("adjacency constraint does not hold")?;
+
+    let range = PolynomialBatch::<F, C, D>::from_coeffs(
+        split_quotient(&q_range, degree),
+        fri_config.rate_bits,
+        true,
+        fri_config.cap_height,
+        &mut TimingTree::default(),
+        Some(&fft_root_table),
+    );
+    challenger.observe_cap::<H>(&range.merkle_tree.cap);
+
+    let zeta = challenger.get_extension_challenge::<D>();
+    let instance = fri_instance(degree_bits, zeta);
+
+    let oracles = [&trace, &argument, &range];
+    let openings = FriOpenings {
+        batches: instance
+            .batches
+            .iter()
+            .map(|batch| FriOpeningBatch {
+                values: batch
+                    .polynomials
+                    .par_iter()
+                    .map(|p| {
+                        oracles[p.oracle_index].polynomials[p.polynomial_index]
+                            .to_extension::<D>()
+                            .eval(batch.point)
+                    })
+                    .collect(),
+            })
+            .collect(),
+    };
+    challenger.observe_openings(&openings);
+
+    let opening_proof = PolynomialBatch::<F, C, D>::prove_openings(
+        &instance,
+        &oracles,
+        &mut challenger,
+        &fri_config.fri_params(degree_bits, true),
+        &mut TimingTree::default(),
+    );
+
+    Ok(RangeProof {
+        degree_bits,
+        trace_cap: trace.merkle_tree.cap.clone(),
+        argument_cap: argument.merkle_tree.cap.clone(),
+        range_cap: range.merkle_tree.cap.clone(),
+        openings,
+        opening_proof,
+    })
+}
+
+/// Verifies a proof that every committed pixel lies in [0, 2^range_bits).
+pub fn verify(proof: &RangeProof, range_bits: usize, fri_config: &FriConfig) -> Result<()> {
+    let degree_bits = proof.degree_bits;
+    let degree = 1usize << degree_bits;
+    let pixel_range = 1usize << range_bits;
+    ensure!(pixel_range < degree, "the domain is too small for the range table");
+
+    // Replay the prover's transcript
+    let mut challenger = Challenger::<F, H>::new();
+    challenger.observe_cap::<H>(&proof.trace_cap);
+    let gamma = challenger.get_challenge();
+    challenger.observe_cap::<H>(&proof.argument_cap);
+    challenger.observe_cap::<H>(&proof.range_cap);
+    let zeta = challenger.get_extension_challenge::<D>();
+    ensure!(
+        zeta.exp_u64(degree as u64) != FE::ONE,
+        "zeta must not lie in the evaluation domain"
+    );
+    let instance = fri_instance(degree_bits, zeta);
+    ensure!(
+        proof.openings.batches.len() == instance.batches.len()
+            && proof
+                .openings
+                .batches
+                .iter()
+                .zip(&instance.batches)
+                .all(|(opening, batch)| opening.values.len() == batch.polynomials.len()),
+        "the openings do not match the opened polynomials"
+    );
+    challenger.observe_openings(&proof.openings);
+
+    let fri_challenges = challenger.fri_challenges::<C, D>(
+        &proof.opening_proof.commit_phase_merkle_caps,
+        &proof.opening_proof.final_poly,
+        proof.opening_proof.pow_witness,
+        degree_bits,
+        fri_config,
+    );
+    verify_fri_proof::<F, C, D>(
+        &instance,
+        &proof.openings,
+        &fri_challenges,
+        &[
+            proof.trace_cap.clone(),
+            proof.argument_cap.clone(),
+            proof.range_cap.clone(),
+        ],
+        &proof.opening_proof,
+        &fri_config.fri_params(degree_bits, true),
+    )?;
+
+    let trace = |point: usize, poly: usize| proof.openings.batches[point].values[poly];
+    let argument =
+        |point: usize, poly: usize| proof.openings.batches[point].values[NUM_TRACE_POLYS + poly];
+    // The range oracle is only opened at zeta, after the other two
+    let range = |poly: usize| proof.openings.batches[ZETA].values[NUM_TRACE_POLYS + NUM_ARGUMENT_POLYS + poly];
+
+    // w is public, so the verifier evaluates the range table on its own
+    let w = PolynomialValues::new(table_values(pixel_range, degree))
+        .ifft()
+        .to_extension::<D>();
+    for (point, batch) in instance.batches.iter().enumerate() {
+        ensure!(
+            trace(point, W) == w.eval(batch.point),
+            "w is not the range table"
+        );
+    }
+
+    let gamma = FE::from(gamma);
+    let omega = F::primitive_root_of_unity(degree_bits);
+    let n_1_zeta = FE::from(omega.exp_u64((degree - 1) as u64)) - zeta;
+    let vanishing_poly_zeta = zeta.exp_u64(degree as u64) - FE::ONE;
+
+    // Check (c_prod[omega*zeta] - c_prod[zeta](gamma + c[zeta])) * n_1[zeta] = q_c[zeta] * Z_H[zeta]
+    for (prod, col, q) in [(W_PROD, W, Q_W), (V_PROD, V, Q_V), (Z_PROD, Z, Q_Z)] {
+        ensure!(
+            (argument(ZETA_NEXT, prod) - argument(ZETA, prod) * (gamma + trace(ZETA, col)))
+                * n_1_zeta
+                == argument(ZETA, q) * vanishing_poly_zeta,
+            "grand product constraint does not hold"
+        );
+    }
+
+    // Check the halves of q_range have fewer than N/2 coefficients, then put it back together
+    let zeta_half = zeta.exp_u64((degree / 2) as u64);
+    ensure!(
+        range(Q_RANGE_LO_SHIFTED) == zeta_half * range(Q_RANGE_LO)
+            && range(Q_RANGE_HI_SHIFTED) == zeta_half * range(Q_RANGE_HI),
+        "q_range exceeds the degree bound of the range oracle"
+    );
+    let q_range = range(Q_RANGE_LO) + zeta_half * range(Q_RANGE_HI);
+
+    // Check (z[omega*zeta] - z[zeta])(1 - (z[omega*zeta] - z[zeta])) * n_1[zeta] = q_range[zeta] * Z_H[zeta]
+    let diff = trace(ZETA_NEXT, Z) - trace(ZETA, Z);
+    ensure!(
+        diff * (FE::ONE - diff) * n_1_zeta == q_range * vanishing_poly_zeta,
+        "adjacency constraint does not hold"
+    );
+
+    // The grand products start at 1 and z walks from 0 to PIXEL_RANGE - 1
+    for prod in [W_PROD, V_PROD, Z_PROD] {
+        ensure!(
+            argument(FIRST_ROW, prod) == FE::ONE,
+            "grand product does not start at 1"
+        );
+    }
+    ensure!(trace(FIRST_ROW, Z) == FE::ZERO, "z does not start at 0");
+    ensure!(
+        trace(LAST_ROW, Z) == FE::from_canonical_usize(pixel_range - 1),
+        "z does not end at PIXEL_RANGE - 1"
+    );
+
+    // The full products satisfy w_prod * v_prod = z_prod * gamma^N, as z drops N padding zeros
+    let full_product = |prod: usize, col: usize| {
+        argument(LAST_ROW, prod) * (gamma + trace(LAST_ROW, col))
+    };
+    ensure!(
+        full_product(W_PROD, W) * full_product(V_PROD, V)
+            == full_product(Z_PROD, Z) * gamma.exp_u64(degree as u64),
+        "z is not a permutation of w and v"
+    );
+
+    Ok(())
+}
+
+/// The trace and argument oracles are opened at zeta, omega * zeta, the first and the last row,
+/// the range oracle only at zeta.
+fn fri_instance(degree_bits: usize, zeta: FE) -> FriInstanceInfo<F, D> {
+    let g = FE::primitive_root_of_unity(degree_bits);
+    let polys = [
+        FriPolynomialInfo::from_range(TRACE, 0..NUM_TRACE_POLYS),
+        FriPolynomialInfo::from_range(ARGUMENT, 0..NUM_ARGUMENT_POLYS),
+    ]
+    .concat();
+    let polys_at_zeta = [
+        polys.clone(),
+        FriPolynomialInfo::from_range(RANGE, 0..NUM_RANGE_POLYS),
+    ]
+    .concat();
+    let points = [zeta, g * zeta, FE::ONE, g.exp_u64((1 << degree_bits) - 1)];
+    FriInstanceInfo {
+        oracles: [NUM_TRACE_POLYS, NUM_ARGUMENT_POLYS, NUM_RANGE_POLYS]
+            .into_iter()
+            .map(|num_polys| FriOracleInfo {
+                num_polys,
+                blinding: true,
+            })
+            .collect(),
+        batches: points
+            .into_iter()
+            .enumerate()
+            .map(|(i, point)| FriBatchInfo {
+                point,
+                polynomials: if i == ZETA {
+                    polys_at_zeta.clone()
+                } else {
+                    polys.clone()
+                },
+            })
+            .collect(),
+    }
+}
+
+/// w_vals = [0, 1,...,PIXEL_RANGE - 1, 0, 0, ..., 0]
+pub(crate) fn table_values(pixel_range: usize, degree: usize) -> Vec<F> {
+    let mut w_vals: Vec<F> = (0..pixel_range).map(F::from_canonical_usize).collect();
+    w_vals.resize(degree, F::ZERO);
+    w_vals
+}
+
+/// Splits a quotient of N coefficients into q_lo + X^(N/2) q_hi, and returns q_lo, q_hi, X^(N/2) q_lo
+/// and X^(N/2) q_hi, all padded to N coefficients.
+fn split_quotient(q: &PolynomialCoeffs<F>, degree: usize) -> Vec<PolynomialCoeffs<F>> {
+    let half = degree / 2;
+    let (lo, hi) = q.coeffs.split_at(half);
+    let padded = |coeffs: &[F]| {
+        let mut padded = coeffs.to_vec();
+        padded.resize(degree, F::ZERO);
+        PolynomialCoeffs::new(padded)
+    };
+    let shifted = |coeffs: &[F]| {
+        let mut shifted = vec![F::ZERO; half];
+        shifted.extend_from_slice(coeffs);
+        PolynomialCoeffs::new(shifted)
+    };
+    vec![padded(lo), padded(hi), shifted(lo), shifted(hi)]
+}
+
+/// [1, (c_0 + gamma), [(c_0 + gamma)(c_1 + gamma)],...,[(c_0 + gamma)...(c_{N-2} + gamma)]]
+fn grand_product_values(vals: &[F], gamma: F) -> Vec<F> {
+    let mut product = F::ONE;
+    let mut prod_vals = Vec::with_capacity(vals.len());
+    prod_vals.push(product);
+    for &val in &vals[..vals.len() - 1] {
+        product *= val + gamma;
+        prod_vals.push(product);
+    }
+    prod_vals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::FriPreset;
+    use plonky2::field::types::Sample;
+
+    fn fri_config() -> FriConfig {
+        FriPreset::default().config()
+    }
+
+    #[test]
+    fn test_values_in_range() -> Result<()> {
+        let values: Vec<u64> = (0..16).chain([31, 0, 7]).collect();
+        let proof = prove(&values, 5, &fri_config())?;
+        verify(&proof, 5, &fri_config())
+    }
+
+    #[test]
+    fn test_value_out_of_range() {
+        assert!(prove(&[3, 32, 7], 5, &fri_config()).is_err());
+    }
+
+    #[test]
+    fn test_wrong_range() -> Result<()> {
+        let proof = prove(&[3, 17, 7], 5, &fri_config())?;
+        assert!(verify(&proof, 4, &fri_config()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_quotient() {
+        let degree = 16;
+        let q = PolynomialCoeffs::new(F::rand_vec(degree));
+        let x = F::rand();
+        let x_half = x.exp_u64(degree as u64 / 2);
+        let halves = split_quotient(&q, degree);
+        let (lo, hi) = (halves[Q_RANGE_LO].eval(x), halves[Q_RANGE_HI].eval(x));
+        assert_eq!(lo + x_half * hi, q.eval(x));
+        assert_eq!(halves[Q_RANGE_LO_SHIFTED].eval(x), x_half * lo);
+        assert_eq!(halves[Q_RANGE_HI_SHIFTED].eval(x), x_half * hi);
+        // The halves fit the degree bound of the range oracle
+        for half in &halves[..2] {
+            assert!(half.degree_plus_one() <= degree / 2);
+        }
+    }
+
+    #[test]
+    fn test_tampered_range_quotient() -> Result<()> {
+        let mut proof = prove(&[3, 17, 7], 5, &fri_config())?;
+        let q_range_lo = NUM_TRACE_POLYS + NUM_ARGUMENT_POLYS + Q_RANGE_LO;
+        proof.openings.batches[ZETA].values[q_range_lo] += FE::ONE;
+        assert!(verify(&proof, 5, &fri_config()).is_err());
+
+        // The shifted halves must match the halves
+        let mut proof = prove(&[3, 17, 7], 5, &fri_config())?;
+        let q_range_hi_shifted = NUM_TRACE_POLYS + NUM_ARGUMENT_POLYS + Q_RANGE_HI_SHIFTED;
+        proof.openings.batches[ZETA].values[q_range_hi_shifted] += FE::ONE;
+        assert!(verify(&proof, 5, &fri_config()).is_err());
+
+        // The range oracle is only opened at zeta
+        let mut proof = prove(&[3, 17, 7], 5, &fri_config())?;
+        proof.openings.batches[ZETA_NEXT].values.push(FE::ONE);
+        assert!(verify(&proof, 5, &fri_config()).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,123 @@
+//! Quotients by the vanishing polynomial Z_H(X) = X^N - 1, computed pointwise on a coset.
+//!
+//! Multiplying the constraint polynomials coefficient-wise and dividing the product takes quadratic
+//! time. Instead every polynomial of a constraint is evaluated on the coset shift * K of a domain K
+//! 2^quotient_bits times larger than H, where the constraint is evaluated row by row and divided by
+//! the values of Z_H, which are not zero outside of H. Interpolating back gives the quotient in
+//! O(N log N), as long as the numerator has fewer than |K| coefficients.
+
+use anyhow::{ensure, Result};
+use plonky2::field::fft::{fft_root_table, FftRootTable};
+use plonky2::field::polynomial::{PolynomialCoeffs, PolynomialValues};
+use plonky2::field::types::Field;
+
+use crate::F;
+
+/// The coset shift * K on which the constraints are evaluated.
+pub(crate) struct Coset {
+    degree_bits: usize,
+    quotient_bits: usize,
+    fft_root_table: FftRootTable<F>,
+    /// 1 / Z_H on the coset, which has period 2^quotient_bits.
+    z_h_inverses: Vec<F>,
+}
+
+impl Coset {
+    /// A coset for constraints whose numerators have fewer than 2^(degree_bits + quotient_bits)
+    /// coefficients.
+    pub(crate) fn new(degree_bits: usize, quotient_bits: usize) -> Self {
+        // x^N = shift^N * g^(N i), with g^N a primitive 2^quotient_bits-th root of unity
+        let shift_n = F::coset_shift().exp_power_of_2(degree_bits);
+        let z_h: Vec<F> = F::primitive_root_of_unity(quotient_bits)
+            .powers()
+            .take(1 << quotient_bits)
+            .map(|root| shift_n * root - F::ONE)
+            .collect();
+        Coset {
+            degree_bits,
+            quotient_bits,
+            fft_root_table: fft_root_table(1 << (degree_bits + quotient_bits)),
+            z_h_inverses: F::batch_multiplicative_inverse(&z_h),
+        }
+    }
+
+    /// The size of the coset.
+    pub(crate) fn len(&self) -> usize {
+        1 << (self.degree_bits + self.quotient_bits)
+    }
+
+    /// The points x of the coset.
+    pub(crate) fn points(&self) -> Vec<F> {
+        let generator = F::primitive_root_of_unity(self.degree_bits + self.quotient_bits);
+        F::cyclic_subgroup_coset_known_order(generator, F::coset_shift(), self.len())
+    }
+
+    /// Evaluates a polynomial of N coefficients on the coset.
+    pub(crate) fn lde(&self, poly: &PolynomialCoeffs<F>) -> Vec<F> {
+        poly.lde(self.quotient_bits)
+            .coset_fft_with_options(
+                F::coset_shift(),
+                Some(self.quotient_bits),
+                Some(&self.fft_root_table),
+            )
+            .values
+    }
+
+    /// Turns the evaluations of c(X) into those of c(omega * X): omega is the 2^quotient_bits-th
+    /// power of the generator of the coset, so this is a rotation.
+    pub(crate) fn next(&self, values: &[F]) -> Vec<F> {
+        let mut rotated = values.to_vec();
+        rotated.rotate_left(1 << self.quotient_bits);
+        rotated
+    }
+
+    /// Divides the evaluations of a constraint by Z_H and interpolates the quotient, padded to N
+    /// coefficients.
+    ///
+    /// Each constraint has degree 2 in the trace polynomials and is multiplied by at most n_1, so
+    /// its numerator has degree at most 2(N - 1) + 1 and its quotient fewer than N coefficients,
+    /// which is the degree bound FRI checks. If Z_H doesn't divide the numerator, what we
+    /// interpolate is not a polynomial of that degree and the prover fails.
+    pub(crate) fn quotient(&self, constraint: Vec<F>) -> Result<PolynomialCoeffs<F>> {
+        let period = self.z_h_inverses.len();
+        let values = constraint
+            .into_iter()
+            .enumerate()
+            .map(|(i, c)| c * self.z_h_inverses[i % period])
+            .collect();
+        let mut q = PolynomialValues::new(values).coset_ifft(F::coset_shift());
+        let degree = 1 << self.degree_bits;
+        ensure!(
+            q.coeffs[degree..].iter().all(|c| c.is_zero()),
+            "the constraint is not divisible by the vanishing polynomial"
+        );
+        q.coeffs.truncate(degree);
+        Ok(q)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plonky2::field::types::Sample;
+
+    #[test]
+    fn test_quotient() -> Result<()> {
+        let degree_bits = 4;
+        let coset = Coset::new(degree_bits, 1);
+        let p = PolynomialCoeffs::new(F::rand_vec(1 << degree_bits));
+
+        // p(X) * Z_H(X) is divisible, p(X) * Z_H(X) + 1 is not
+        let p_lde = coset.lde(&p);
+        let z_h = |x: F| x.exp_power_of_2(degree_bits) - F::ONE;
+        let divisible = p_lde.iter().zip(coset.points()).map(|(&p, x)| p * z_h(x)).collect();
+        assert_eq!(coset.quotient(divisible)?, p);
+        let not_divisible = p_lde
+            .iter()
+            .zip(coset.points())
+            .map(|(&p, x)| p * z_h(x) + F::ONE)
+            .collect();
+        assert!(coset.quotient(not_divisible).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,181 @@
+//! FRI parameters of the range proof: named security presets, overrides and soundness estimates.
+//!
+//! The soundness of a FRI proof is estimated with the usual conjecture (also used by plonky2 for its
+//! standard configurations): every query round contributes `rate_bits` bits, grinding adds
+//! `proof_of_work_bits`, and the total is capped by the size of the extension field the challenges
+//! are drawn from.
+//!
+//! The lookup arguments draw gamma (and alpha) from the base field, not from the extension: a false
+//! statement passes their identity with probability up to 2N / |F| by Schwartz-Zippel, as both sides
+//! have degree at most 2N in gamma. This caps the soundness of the whole proof well below the FRI
+//! presets, at 62 - log2(N) bits.
+
+use anyhow::{ensure, Result};
+use clap::{Args, ValueEnum};
+use plonky2::field::types::Field;
+use plonky2::fri::reduction_strategies::FriReductionStrategy;
+use plonky2::fri::FriConfig;
+use serde::Deserialize;
+
+use crate::{D, F};
+
+/// A named set of FRI parameters, named after the soundness of the FRI opening proof alone.
+///
+/// The lookup argument caps the soundness of the whole range proof at 62 - log2(N) bits whatever the
+/// preset, as its challenges are drawn from the base field: see [`soundness_bits`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum FriPreset {
+    /// Few queries and a low blowup, for tests only: a handful of bits of soundness.
+    FastTest,
+    /// 100 bits of conjectured FRI security, the parameters of plonky2's standard recursion config.
+    #[default]
+    #[value(name = "fri-100-bit")]
+    Fri100,
+    /// 128 bits of conjectured FRI security, at the cost of 10 more query rounds.
+    #[value(name = "fri-128-bit")]
+    Fri128,
+}
+
+impl FriPreset {
+    pub fn config(self) -> FriConfig {
+        let (rate_bits, num_query_rounds) = match self {
+            FriPreset::FastTest => (1, 8),
+            FriPreset::Fri100 => (3, 28),
+            FriPreset::Fri128 => (3, 38),
+        };
+        FriConfig {
+            rate_bits, // trade-off between proof size and computational efficiency.
+            cap_height: 4, // try using the greatest-as-possible value to reduce the work of the verifier
+            proof_of_work_bits: if self == FriPreset::FastTest { 0 } else { 16 },
+            reduction_strategy: FriReductionStrategy::ConstantArityBits(4, 5),
+            num_query_rounds,
+        }
+    }
+}
+
+/// Custom FRI parameters replacing those of a preset, from the command line or a JSON file.
+#[derive(Args, Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FriOverrides {
+    /// log2 of the blowup factor of the low-degree extension.
+    #[arg(long)]
+    pub rate_bits: Option<usize>,
+    /// Height of the Merkle caps sent instead of the roots.
+    #[arg(long)]
+    pub cap_height: Option<usize>,
+    /// Number of grinding bits.
+    #[arg(long)]
+    pub proof_of_work_bits: Option<u32>,
+    /// Number of FRI query rounds.
+    #[arg(long)]
+    pub num_query_rounds: Option<usize>,
+    /// log2 of the arity of every FRI reduction step.
+    #[arg(long)]
+    pub arity_bits: Option<usize>,
+    /// Stop reducing once the polynomial has at most 2^final_poly_bits coefficients.
+    #[arg(long)]
+    pub final_poly_bits: Option<usize>,
+}
+
+impl FriOverrides {
+    /// Returns `self`, with the parameters it doesn't set taken from `other`.
+    pub fn or(self, other: FriOverrides) -> FriOverrides {
+        FriOverrides {
+            rate_bits: self.rate_bits.or(other.rate_bits),
+            cap_height: self.cap_height.or(other.cap_height),
+            proof_of_work_bits: self.proof_of_work_bits.or(other.proof_of_work_bits),
+            num_query_rounds: self.num_query_rounds.or(other.num_query_rounds),
+            arity_bits: self.arity_bits.or(other.arity_bits),
+            final_poly_bits: self.final_poly_bits.or(other.final_poly_bits),
+        }
+    }
+
+    /// Applies the overrides to `config`, checking the result is a usable configuration.
+    pub fn apply(&self, config: FriConfig) -> Result<FriConfig> {
+        let (arity_bits, final_poly_bits) = match config.reduction_strategy {
+            FriReductionStrategy::ConstantArityBits(arity_bits, final_poly_bits) => {
+                (arity_bits, final_poly_bits)
+            }
+            _ => unreachable!("presets use a constant arity"),
+        };
+        let arity_bits = self.arity_bits.unwrap_or(arity_bits);
+        let final_poly_bits = self.final_poly_bits.unwrap_or(final_poly_bits);
+        let config = FriConfig {
+            rate_bits: self.rate_bits.unwrap_or(config.rate_bits),
+            cap_height: self.cap_height.unwrap_or(config.cap_height),
+            proof_of_work_bits: self.proof_of_work_bits.unwrap_or(config.proof_of_work_bits),
+            reduction_strategy: FriReductionStrategy::ConstantArityBits(arity_bits, final_poly_bits),
+            num_query_rounds: self.num_query_rounds.unwrap_or(config.num_query_rounds),
+        };
+
+        ensure!(config.rate_bits > 0, "the rate must be below 1");
+        ensure!(config.num_query_rounds > 0, "there must be at least one query round");
+        ensure!(
+            config.proof_of_work_bits < 64,
+            "cannot grind {} bits",
+            config.proof_of_work_bits
+        );
+        ensure!(
+            arity_bits > 0 && arity_bits <= final_poly_bits + 1,
+            "the arity bits must be in [1, final_poly_bits + 1]"
+        );
+        Ok(config)
+    }
+}
+
+/// Estimated bits of security of the FRI opening proof alone, under the conjecture above.
+pub fn fri_soundness_bits(config: &FriConfig) -> usize {
+    let query_bits = config.rate_bits * config.num_query_rounds;
+    (query_bits + config.proof_of_work_bits as usize).min(D * F::BITS)
+}
+
+/// Estimated bits of security of a range proof over a domain of size 2^degree_bits: the lesser of
+/// the FRI soundness and that of the lookup argument with its base-field challenge.
+pub fn soundness_bits(config: &FriConfig, degree_bits: usize) -> usize {
+    let argument_bits = (F::BITS - 1).saturating_sub(degree_bits + 1);
+    fri_soundness_bits(config).min(argument_bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plookup;
+
+    #[test]
+    fn test_presets_soundness() {
+        assert!(fri_soundness_bits(&FriPreset::FastTest.config()) < 20);
+        assert_eq!(fri_soundness_bits(&FriPreset::Fri100.config()), 100);
+        assert_eq!(fri_soundness_bits(&FriPreset::Fri128.config()), 128);
+
+        // The base-field challenge of the lookup argument bounds the whole proof
+        assert_eq!(soundness_bits(&FriPreset::Fri128.config(), 10), 52);
+        assert_eq!(soundness_bits(&FriPreset::FastTest.config(), 10), 8);
+    }
+
+    #[test]
+    fn test_overrides() -> Result<()> {
+        let file: FriOverrides = serde_json::from_str(r#"{"rate_bits": 4, "arity_bits": 3}"#)?;
+        let cli = FriOverrides {
+            rate_bits: Some(2),
+            ..Default::default()
+        };
+        let config = cli.or(file).apply(FriPreset::Fri100.config())?;
+        assert_eq!(config.rate_bits, 2);
+        assert_eq!(config.num_query_rounds, 28);
+        assert_eq!(
+            config.reduction_strategy,
+            FriReductionStrategy::ConstantArityBits(3, 5)
+        );
+
+        let values: Vec<u64> = (0..20).collect();
+        let proof = plookup::prove(&values, 5, &config)?;
+        plookup::verify(&proof, 5, &config)?;
+
+        let invalid = FriOverrides {
+            num_query_rounds: Some(0),
+            ..Default::default()
+        };
+        assert!(invalid.apply(config).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,123 @@
+//! Encoding of signed integers as field elements.
+//!
+//! A negative x is encoded as p - |x|, so field arithmetic on encodings matches integer arithmetic
+//! as long as no intermediate result leaves (-p, p). Since the Goldilocks modulus p = 2^64 - 2^32 + 1
+//! is smaller than 2^64, the field cannot tell every pair of i64 values apart (e.g. i64::MIN and
+//! 2^63 - 2^32 + 1 share an encoding), so decoding needs to know the range the value came from.
+
+use anyhow::{ensure, Result};
+use plonky2::field::types::{Field, PrimeField64};
+
+/// Encodes a signed integer as a field element.
+pub fn encode_i64<F: Field>(x: i64) -> F {
+    // |x| <= 2^63 < p, so both branches are canonical
+    if x >= 0 {
+        F::from_canonical_u64(x as u64)
+    } else {
+        -F::from_canonical_u64(x.unsigned_abs())
+    }
+}
+
+/// Encodes a slice of signed integers as field elements.
+pub fn encode_i64_slice<F: Field>(values: &[i64]) -> Vec<F> {
+    values.iter().map(|&x| encode_i64(x)).collect()
+}
+
+/// Decodes a field element to the integer in [lower, upper] it encodes.
+///
+/// Fails if the range is too wide to decode unambiguously (upper - lower >= p), or if no integer
+/// of the range encodes to `x`.
+pub fn decode_i64<F: PrimeField64>(x: F, lower: i64, upper: i64) -> Result<i64> {
+    ensure!(lower <= upper, "empty range [{}, {}]", lower, upper);
+    let width = (upper as i128 - lower as i128) as u128;
+    ensure!(
+        width < F::ORDER as u128,
+        "range [{}, {}] is too wide to decode unambiguously",
+        lower,
+        upper
+    );
+
+    // The offset of x from the lower bound, reduced mod p
+    let offset = (x - encode_i64::<F>(lower)).to_canonical_u64();
+    ensure!(
+        offset as u128 <= width,
+        "{} does not encode an integer in [{}, {}]",
+        x,
+        lower,
+        upper
+    );
+    Ok((lower as i128 + offset as i128) as i64)
+}
+
+/// Decodes a field element to the integer of least absolute value it encodes, i.e. the integer in
+/// [-(p - 1) / 2, (p - 1) / 2], which always fits in an i64.
+pub fn decode_i64_centered<F: PrimeField64>(x: F) -> i64 {
+    let half = ((F::ORDER - 1) / 2) as i64;
+    decode_i64(x, -half, half)
+        .expect("every field element encodes an integer of the centered range")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plonky2::field::goldilocks_field::GoldilocksField as F;
+    use plonky2::field::types::Field64;
+    use proptest::prelude::*;
+
+    const HALF: i64 = ((F::ORDER - 1) / 2) as i64;
+
+    #[test]
+    fn test_boundaries() {
+        for x in [i64::MIN, i64::MIN + 1, -1, 0, 1, i64::MAX - 1, i64::MAX] {
+            assert_eq!(decode_i64(encode_i64::<F>(x), x, x).unwrap(), x);
+        }
+        assert_eq!(encode_i64::<F>(-1), F::NEG_ONE);
+        assert_eq!(encode_i64::<F>(i64::MIN), -F::from_canonical_u64(1 << 63));
+    }
+
+    #[test]
+    fn test_i64_min_is_ambiguous() {
+        // i64::MIN and i64::MIN + p collide, so decoding must be told which one is meant
+        let collision = (i64::MIN as i128 + F::ORDER as i128) as i64;
+        assert_eq!(encode_i64::<F>(i64::MIN), encode_i64::<F>(collision));
+        assert_eq!(
+            decode_i64(encode_i64::<F>(i64::MIN), i64::MIN, 0).unwrap(),
+            i64::MIN
+        );
+        assert_eq!(
+            decode_i64(encode_i64::<F>(i64::MIN), 0, i64::MAX).unwrap(),
+            collision
+        );
+        assert!(decode_i64(F::ZERO, i64::MIN, i64::MAX).is_err());
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        assert!(decode_i64(encode_i64::<F>(11), -10, 10).is_err());
+        assert!(decode_i64(encode_i64::<F>(-11), -10, 10).is_err());
+        assert!(decode_i64(encode_i64::<F>(0), 1, 0).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn test_roundtrip_in_range(x: i64, below in 0u64..1 << 40, above in 0u64..1 << 40) {
+            let lower = x.saturating_sub_unsigned(below);
+            let upper = x.saturating_add_unsigned(above);
+            prop_assert_eq!(decode_i64(encode_i64::<F>(x), lower, upper).unwrap(), x);
+        }
+
+        #[test]
+        fn test_roundtrip_centered(x in -HALF..=HALF) {
+            prop_assert_eq!(decode_i64_centered(encode_i64::<F>(x)), x);
+        }
+
+        #[test]
+        fn test_encoding_preserves_differences(a: i64, b: i64) {
+            // The head-tail check relies on x - lower being the integer difference
+            let diff = a as i128 - b as i128;
+            let expected = diff.rem_euclid(F::ORDER as i128) as u64;
+            let actual = (encode_i64::<F>(a) - encode_i64::<F>(b)).to_canonical_u64();
+            prop_assert_eq!(actual, expected);
+        }
+    }
+}
@@ -0,0 +1,214 @@
+use anyhow::{ensure, Context as _, Result};
+use fibonacci_lib::{Context, FILTER_BITS};
+use plonky2::field::extension::Extendable;
+use plonky2::hash::hash_types::RichField;
+use plonky2::iop::target::Target;
+use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+
+/// The number of bits of a pixel.
+const PIXEL_BITS: usize = 8;
+
+/// The targets of a resize circuit.
+pub struct ResizeTargets {
+    /// The private source plane, row by row.
+    pub src: Vec<Target>,
+    /// The resized plane, row by row. These are the public inputs of the circuit.
+    pub dst: Vec<Target>,
+}
+
+/// Builds a circuit proving that a public `dst_w` x `dst_h` plane is the resize of a private
+/// `src_w` x `src_h` plane computed by `fibonacci_lib::scale_image`.
+///
+/// The filter coefficients are derived by `Context::new` exactly as in the zkVM program and become
+/// circuit constants. Fails if a filter starts before the source, which `scale_image` can't resize
+/// either, see `Context::starts_inside_source`. Like `scale_image`, the resize is separable: a
+/// horizontal pass produces an intermediate `dst_w` x `src_h` plane, then a vertical pass produces
+/// the output. Every pixel of both passes is the rounded fixed-point weighted sum
+///   out = (sum_z coeff_z * in_z + 2^(FILTER_BITS - 1)) >> FILTER_BITS
+/// which we enforce by splitting the sum into out * 2^FILTER_BITS + remainder and range checking
+/// the remainder to FILTER_BITS bits and out to 8 bits.
+pub fn build_resize_circuit<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    src_w: usize,
+    src_h: usize,
+    dst_w: usize,
+    dst_h: usize,
+) -> Result<ResizeTargets> {
+    let context = Context::new(src_w as i32, src_h as i32, dst_w as i32, dst_h as i32)
+        .context("failed to initialize the resize filters")?;
+    ensure!(
+        context.starts_inside_source(),
+        "scale_image can't resize {}x{} to {}x{}: a filter starts before the source",
+        src_w,
+        src_h,
+        dst_w,
+        dst_h
+    );
+
+    let src = builder.add_virtual_targets(src_w * src_h);
+    for &pixel in &src {
+        builder.range_check(pixel, PIXEL_BITS);
+    }
+
+    // Horizontal pass: src_w x src_h -> dst_w x src_h
+    let mut tmp = Vec::with_capacity(dst_w * src_h);
+    for y in 0..src_h {
+        for x in 0..dst_w {
+            let (pos, coeffs) = context.horizontal_filter(x);
+            let taps = filter_taps(pos, coeffs, src_w)
+                .map(|(col, coeff)| (coeff, src[y * src_w + col]))
+                .collect::<Vec<_>>();
+            tmp.push(rounded_weighted_sum(builder, &taps));
+        }
+    }
+
+    // Vertical pass: dst_w x src_h -> dst_w x dst_h
+    let mut dst = Vec::with_capacity(dst_w * dst_h);
+    for y in 0..dst_h {
+        let (pos, coeffs) = context.vertical_filter(y);
+        for x in 0..dst_w {
+            let taps = filter_taps(pos, coeffs, src_h)
+                .map(|(row, coeff)| (coeff, tmp[row * dst_w + x]))
+                .collect::<Vec<_>>();
+            dst.push(rounded_weighted_sum(builder, &taps));
+        }
+    }
+    builder.register_public_inputs(&dst);
+
+    Ok(ResizeTargets { src, dst })
+}
+
+/// Assigns the private source plane to a resize circuit. The intermediate and output pixels are
+/// computed by the circuit's witness generators.
+pub fn set_resize_witness<F: RichField>(
+    pw: &mut PartialWitness<F>,
+    targets: &ResizeTargets,
+    src: &[u8],
+) -> Result<()> {
+    ensure!(
+        src.len() == targets.src.len(),
+        "circuit expects a plane of {} pixels, got {}",
+        targets.src.len(),
+        src.len()
+    );
+
+    for (&target, &pixel) in targets.src.iter().zip(src) {
+        pw.set_target(target, F::from_canonical_u8(pixel));
+    }
+    Ok(())
+}
+
+/// Returns the (source index, coefficient) pairs of a filter starting inside the source, skipping
+/// the taps past its end like `scale_image` does.
+fn filter_taps(pos: i32, coeffs: &[i16], len: usize) -> impl Iterator<Item = (usize, i16)> + '_ {
+    let pos = pos as usize;
+    coeffs
+        .iter()
+        .enumerate()
+        .map(move |(z, &coeff)| (pos + z, coeff))
+        .filter(move |&(index, _)| index < len)
+}
+
+/// Returns a target holding (sum_z coeff_z * x_z + 2^(FILTER_BITS - 1)) >> FILTER_BITS, checked to
+/// be a pixel.
+fn rounded_weighted_sum<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    taps: &[(i16, Target)],
+) -> Target {
+    let filter_bits = FILTER_BITS as usize;
+    let mut sum = builder.constant(F::from_canonical_u64(1 << (filter_bits - 1)));
+    for &(coeff, x) in taps {
+        // scale_image accumulates in u32, a negative coefficient would wrap around
+        assert!(coeff >= 0, "negative filter coefficient {}", coeff);
+        if coeff != 0 {
+            sum = builder.mul_const_add(F::from_canonical_u64(coeff as u64), x, sum);
+        }
+    }
+
+    // sum = out * 2^FILTER_BITS + remainder, with remainder < 2^FILTER_BITS and out < 2^8
+    let (_remainder, out) = builder.split_low_high(sum, filter_bits, filter_bits + PIXEL_BITS);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plonky2::field::types::Field;
+    use plonky2::plonk::circuit_data::CircuitConfig;
+    use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    /// A deterministic plane exercising the whole pixel range.
+    fn test_plane(width: usize, height: usize) -> Vec<u8> {
+        (0..width * height)
+            .map(|i| ((i * 37 + i / width * 91) % 256) as u8)
+            .collect()
+    }
+
+    fn prove_and_verify(
+        src: &[u64],
+        src_w: usize,
+        src_h: usize,
+        dst_w: usize,
+        dst_h: usize,
+    ) -> Result<Vec<F>> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let targets = build_resize_circuit(&mut builder, src_w, src_h, dst_w, dst_h)?;
+        let data = builder.build::<C>();
+
+        // Assign the raw values so out-of-range pixels can be tested
+        let mut pw = PartialWitness::new();
+        for (&target, &pixel) in targets.src.iter().zip(src) {
+            pw.set_target(target, F::from_canonical_u64(pixel));
+        }
+        let proof = data.prove(pw)?;
+        let public_inputs = proof.public_inputs.clone();
+        data.verify(proof)?;
+        Ok(public_inputs)
+    }
+
+    #[test]
+    fn test_matches_native_resize() -> Result<()> {
+        for (src_w, src_h, dst_w, dst_h) in [(8, 8, 2, 2), (13, 9, 3, 2), (10, 13, 5, 3)] {
+            let src = test_plane(src_w, src_h);
+            let mut expected = vec![0u8; dst_w * dst_h];
+            fibonacci_lib::resize_image(
+                &src,
+                &mut expected,
+                src_w as i32,
+                src_h as i32,
+                dst_w as i32,
+                dst_h as i32,
+            );
+
+            let src: Vec<u64> = src.iter().map(|&x| x as u64).collect();
+            let dst = prove_and_verify(&src, src_w, src_h, dst_w, dst_h)?;
+            let expected: Vec<F> = expected.iter().map(|&x| F::from_canonical_u8(x)).collect();
+            assert_eq!(dst, expected);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_filter_before_source() {
+        // The first row of a 2x downscale or of an upscale reads above the image
+        for (src_w, src_h, dst_w, dst_h) in [(10, 6, 5, 3), (5, 3, 12, 7)] {
+            let mut builder =
+                CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+            assert!(build_resize_circuit(&mut builder, src_w, src_h, dst_w, dst_h).is_err());
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "was set twice with different values")]
+    fn test_source_pixel_out_of_range() {
+        let mut src: Vec<u64> = test_plane(8, 8).iter().map(|&x| x as u64).collect();
+        src[9] = 256;
+        prove_and_verify(&src, 8, 8, 2, 2).unwrap();
+    }
+}
@@ -0,0 +1,135 @@
+use anyhow::{ensure, Result};
+use clap::Parser;
+use plonky2::field::types::PrimeField64;
+use plonky2::iop::witness::PartialWitness;
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::CircuitConfig;
+use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+use range_check_input::{read_vector, Format};
+use resize_circuit::{build_resize_circuit, set_resize_witness};
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Instant;
+
+/// Proves with plonky2 that a plane was resized by the bilinear filter of the zkVM program.
+#[derive(Parser, Debug)]
+struct Args {
+    /// The source plane, e.g. one of the channel files written by convert_image_to_yuv.py.
+    #[arg(long, default_value = "resizing_video/sand_480270_Y_channel.txt")]
+    input: PathBuf,
+    #[arg(long, default_value_t = 480)]
+    width: usize,
+    #[arg(long, default_value_t = 270)]
+    height: usize,
+    /// Width of the top-left region of the plane that is resized. The circuit has about 2 gates per
+    /// source pixel, so a whole 480x270 frame needs 2^18 gates and a lot of memory to prove.
+    #[arg(long, default_value_t = 64)]
+    crop_width: usize,
+    #[arg(long, default_value_t = 64)]
+    crop_height: usize,
+    #[arg(long, default_value_t = 16)]
+    output_width: usize,
+    #[arg(long, default_value_t = 16)]
+    output_height: usize,
+    /// Format of the input file (detected from the extension and contents by default).
+    #[arg(long, value_enum)]
+    format: Option<Format>,
+    /// Where to write the proven resized plane.
+    #[arg(long)]
+    output: Option<PathBuf>,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    let config = CircuitConfig::standard_recursion_config();
+    let mut builder = CircuitBuilder::<F, D>::new(config);
+
+    let src: Vec<u64> = read_vector(&args.input, args.format)?;
+    ensure!(
+        src.len() == args.width * args.height,
+        "{} has {} pixels, expected {}x{}",
+        args.input.display(),
+        src.len(),
+        args.width,
+        args.height
+    );
+    ensure!(
+        args.crop_width <= args.width && args.crop_height <= args.height,
+        "crop region must fit in the plane"
+    );
+    let src = src
+        .chunks(args.width)
+        .take(args.crop_height)
+        .flat_map(|row| &row[..args.crop_width])
+        .map(|&x| u8::try_from(x).map_err(|_| anyhow::anyhow!("{} is not a pixel", x)))
+        .collect::<Result<Vec<u8>>>()?;
+
+    let targets = build_resize_circuit(
+        &mut builder,
+        args.crop_width,
+        args.crop_height,
+        args.output_width,
+        args.output_height,
+    )?;
+    println!("Circuit has {} gates", builder.num_gates());
+
+    // Boilerplate code for benchmark, prove and verify
+    let start_build = Instant::now();
+    let data = builder.build::<C>();
+    let build_duration = start_build.elapsed();
+    println!("Circuit built in: {:?}", build_duration);
+
+    let mut pw = PartialWitness::new();
+    set_resize_witness(&mut pw, &targets, &src)?;
+
+    let start_prove = Instant::now();
+    let proof = data.prove(pw)?;
+    let prove_duration = start_prove.elapsed();
+    println!("Proof generated in: {:?}", prove_duration);
+
+    println!("Resize proof generated successfully.");
+
+    // The resized plane is the public input of the proof, check it against the native resize
+    let dst: Vec<u8> = proof
+        .public_inputs
+        .iter()
+        .map(|x| x.to_canonical_u64() as u8)
+        .collect();
+    let mut expected = vec![0u8; args.output_width * args.output_height];
+    fibonacci_lib::resize_image(
+        &src,
+        &mut expected,
+        args.crop_width as i32,
+        args.crop_height as i32,
+        args.output_width as i32,
+        args.output_height as i32,
+    );
+    ensure!(dst == expected, "proven plane differs from the native resize");
+    println!("Proven plane matches the native resize.");
+
+    let start_verify = Instant::now();
+    data.verify(proof)?;
+    let verify_duration = start_verify.elapsed();
+    println!("Proof verified in: {:?}", verify_duration);
+
+    println!("Proof verified successfully.");
+
+    if let Some(path) = &args.output {
+        let mut file = File::create(path)?;
+        for row in dst.chunks(args.output_width) {
+            for pixel in row {
+                write!(file, "{} ", pixel)?;
+            }
+            writeln!(file)?;
+        }
+        println!("Resized plane written to {}", path.display());
+    }
+
+    Ok(())
+}
@@ -230,7 +230,7 @@ fn main() {
     let mut output = vec![0u8; (output_width * output_height) as usize];
 
     let input_path = Path::new(input_file);
-    let file = File::open(&input_path).expect("Failed to open input file");
+    let file = File::open(input_path).expect("Failed to open input file");
     let reader = BufReader::new(file);
 
     for (i, line) in reader.lines().enumerate() {
@@ -265,7 +265,7 @@ fn main() {
     print_data_sample(&output, 64);
 
     let output_path = Path::new(output_file);
-    let mut output_file = File::create(&output_path).expect("Failed to open output file");
+    let mut output_file = File::create(output_path).expect("Failed to open output file");
 
     for i in 0..output_height {
         for j in 0..output_width {